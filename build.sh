@@ -1,14 +1,22 @@
 #!/bin/bash
 set -e
 
+# Newer compilers emit wasm features the NEAR runtime rejects.
+TOOLCHAIN=${TOOLCHAIN:-1.81}
+
 cd navara-name-service-resolver
 
-RUSTFLAGS='-C link-arg=-s' cargo build --target wasm32-unknown-unknown --release
+RUSTFLAGS='-C link-arg=-s' cargo +$TOOLCHAIN build --target wasm32-unknown-unknown --release
 mkdir -p ../out
 cp target/wasm32-unknown-unknown/release/*.wasm ../out/resolver.wasm
 
+cd ../navara-price-oracle-mock
+
+RUSTFLAGS='-C link-arg=-s' cargo +$TOOLCHAIN build --target wasm32-unknown-unknown --release
+cp target/wasm32-unknown-unknown/release/*.wasm ../out/price_oracle_mock.wasm
+
 cd ../navara-name-service-registry
 
-RUSTFLAGS='-C link-arg=-s' cargo build --target wasm32-unknown-unknown --release
+RUSTFLAGS='-C link-arg=-s' cargo +$TOOLCHAIN build --target wasm32-unknown-unknown --release
 mkdir -p ../out
 cp target/wasm32-unknown-unknown/release/*.wasm ../out/registry.wasm
\ No newline at end of file
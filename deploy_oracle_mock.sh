@@ -0,0 +1,12 @@
+# deploy a mock price oracle for USD pricing on testnet, then point the
+# registry to it with set_usd_pricing
+
+near deploy \
+    --wasmFile ./out/price_oracle_mock.wasm \
+    --initFunction "new" \
+    --initArgs '{
+        "owner_id": "michaelng.testnet"
+    }' \
+    --accountId oracle.navara.testnet
+
+near call oracle.navara.testnet set_price '{ "asset_id": "wrap.testnet", "price": { "multiplier": "30000", "decimals": 28 } }' --accountId michaelng.testnet
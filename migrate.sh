@@ -3,7 +3,5 @@
 near deploy \
     --wasmFile ./out/registry.wasm \
     --initFunction "migrate" \
-    --initArgs '{
-        "owner_id": "michaelng.testnet"
-    }' \
-    --accountId navara.testnet
\ No newline at end of file
+    --initArgs '{}' \
+    --accountId navara.testnet
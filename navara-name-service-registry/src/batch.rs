@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+
+use near_sdk::json_types::U128;
+use near_sdk::{PromiseError, StorageUsage};
+
+use crate::*;
+
+/// Names per batch, so that a batch always fits in the gas of one call.
+/// A batch of 20 names of the maximum length measured 99 Tgas of host
+/// calls in `test_register_batch_gas`, within `BATCH_CALLBACK_GAS`.
+pub const MAX_BATCH_SIZE: usize = 20;
+/// Upper bound of the storage of one registered name. Checked before a
+/// batch starts and set aside by pro-rata registrations, so that charging
+/// the storage cannot fail once the name is minted.
+pub const MAX_NAME_STORAGE_BYTES: StorageUsage = 1_500;
+/// Gas of the callback that runs a batch of `MAX_BATCH_SIZE` names after
+/// the price oracle: 8 Tgas per name on top of 20 Tgas.
+pub const BATCH_CALLBACK_GAS: Gas = Gas(180_000_000_000_000);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchRegisterItem {
+    pub name: TokenId,
+    pub owner_id: AccountId,
+    pub years: u64,
+    /// Salt of the matured commitment of `name`, as for `reveal_register`.
+    pub salt: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchExtendItem {
+    pub name: TokenId,
+    pub years: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchResult {
+    pub name: TokenId,
+    pub success: bool,
+    pub charged: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BatchResult {
+    fn failed(name: TokenId, error: String) -> Self {
+        Self { name, success: false, charged: U128(0), error: Some(error) }
+    }
+}
+
+impl Contract {
+    /// Fails when `deposited` and the storage balance of `payer` cannot pay
+    /// the storage of `count` names.
+    fn check_batch_storage(&self, payer: &AccountId, count: usize, deposited: Balance) -> Result<(), String> {
+        let storage_cost = Balance::from(MAX_NAME_STORAGE_BYTES) * count as Balance * env::storage_byte_cost();
+        if deposited + self.storage_available(payer) < storage_cost {
+            return Err(format!("Deposit or storage balance at least {} yoctoNEAR for storage", storage_cost));
+        }
+        Ok(())
+    }
+
+    /// Checks the prepaid gas before a batch waits for the price oracle, so
+    /// that the callback always runs and either registers or refunds.
+    fn require_batch_gas() {
+        assert!(
+            env::prepaid_gas() - env::used_gas() >= BATCH_CALLBACK_GAS + GAS,
+            "Attach at least {} Tgas", (BATCH_CALLBACK_GAS + GAS).0 / 1_000_000_000_000
+        );
+    }
+
+    fn batch_register_price(&self, item: &BatchRegisterItem, base_price: Balance) -> Result<(TokenId, Balance, CryptoHash), String> {
+        let token_id = self.normalize_name(&item.name)?;
+        let commitment = self.check_commitment(&token_id, &item.owner_id, &item.salt)?;
+        if item.years == 0 {
+            return Err("Register at least one year".to_owned());
+        }
+        if self.name_state_of(&token_id) != NameState::Available {
+            return Err("Name not available".to_owned());
+        }
+        if self.is_reserved(&token_id) {
+            return Err("Name is reserved".to_owned());
+        }
+        let price = self.yearly_price_for(&token_id, base_price) * item.years as Balance + self.premium_of(&token_id);
+        Ok((token_id, price, commitment))
+    }
+
+    fn batch_extend_price(&self, item: &BatchExtendItem, payer: &AccountId, base_price: Balance) -> Result<(TokenId, Balance), String> {
+        let token_id = self.normalize_name(&item.name)?;
+        let token = self.nft_token(token_id.to_owned()).ok_or_else(|| "Name not found".to_owned())?;
+        self.check_renewer(&token, payer)?;
+        if self.is_subname(&token_id) {
+            return Err("Subnames follow their parent expiry".to_owned());
+        }
+        if item.years == 0 {
+            return Err("Extend at least one year".to_owned());
+        }
+        let fee = match self.name_state_of(&token_id) {
+            NameState::Active | NameState::Grace => 0,
+            NameState::Redemption => self.redemption_fee,
+            NameState::Available => return Err("Name expired".to_owned()),
+        };
+        let duration = item.years * ONE_YEAR_MILLISECOND;
+        if self.name_expired_date.get(&token_id).unwrap() + duration <= env::block_timestamp_ms() {
+            return Err("Invalid expired date".to_owned());
+        }
+        let price = self.yearly_price_for(&token_id, base_price) * item.years as Balance + fee;
+        Ok((token_id, price))
+    }
+
+    /// Registers every valid item of `items` in this call. Invalid items are
+    /// reported and skipped. Fails without side effects when `deposited`
+    /// does not cover the valid items, otherwise refunds what is left after
+    /// prices and storage.
+    pub fn register_batch_with_price(
+        &mut self,
+        items: Vec<BatchRegisterItem>,
+        payer: AccountId,
+        deposited: Balance,
+        base_price: Balance,
+        referrer: Option<AccountId>
+    ) -> Result<Vec<BatchResult>, String> {
+        let mut seen = HashSet::new();
+        let prices: Vec<Result<(TokenId, Balance, CryptoHash), String>> = items
+            .iter()
+            .map(|item| {
+                let (token_id, price, commitment) = self.batch_register_price(item, base_price)?;
+                if !seen.insert(token_id.to_owned()) {
+                    return Err("Duplicate name".to_owned());
+                }
+                Ok((token_id, price, commitment))
+            })
+            .collect();
+        let total: Balance = prices.iter().flatten().map(|(_, price, _)| price).sum();
+        if deposited < total {
+            return Err(format!("Deposit at least {} yoctoNEAR", total));
+        }
+        self.check_batch_storage(&payer, prices.iter().flatten().count(), deposited - total)?;
+
+        let initial_storage_usage = env::storage_usage();
+        let results = items
+            .into_iter()
+            .zip(prices)
+            .map(|(item, price)| match price {
+                Ok((token_id, price, commitment)) => {
+                    self.internal_remove_commitment(&commitment);
+                    self.record_revenue(RevenueKind::Registration, price);
+                    self.accrue_referral(referrer.to_owned(), price, RevenueKind::Registration);
+                    let token_metadata = self.metadata_by_token(&token_id);
+                    self.internal_register_name(token_id.to_owned(), item.owner_id, token_metadata, item.years * ONE_YEAR_MILLISECOND, price, None);
+                    self.internal_set_storage_payer(&token_id, &payer);
+                    BatchResult { name: token_id, success: true, charged: U128::from(price), error: None }
+                }
+                Err(err) => BatchResult::failed(item.name, err),
+            })
+            .collect();
+        let storage_used = env::storage_usage().saturating_sub(initial_storage_usage);
+        let refund = self.charge_storage(&payer, storage_used, deposited - total);
+        if refund > 0 {
+            Promise::new(payer).transfer(refund);
+        }
+        Ok(results)
+    }
+
+    /// Same as `register_batch_with_price` for renewals.
+    pub fn extend_batch_with_price(
+        &mut self,
+        items: Vec<BatchExtendItem>,
+        payer: AccountId,
+        deposited: Balance,
+        base_price: Balance,
+        referrer: Option<AccountId>
+    ) -> Result<Vec<BatchResult>, String> {
+        let mut seen = HashSet::new();
+        let prices: Vec<Result<(TokenId, Balance), String>> = items
+            .iter()
+            .map(|item| {
+                let (token_id, price) = self.batch_extend_price(item, &payer, base_price)?;
+                if !seen.insert(token_id.to_owned()) {
+                    return Err("Duplicate name".to_owned());
+                }
+                Ok((token_id, price))
+            })
+            .collect();
+        let total: Balance = prices.iter().flatten().map(|(_, price)| price).sum();
+        if deposited < total {
+            return Err(format!("Deposit at least {} yoctoNEAR", total));
+        }
+
+        let results = items
+            .into_iter()
+            .zip(prices)
+            .map(|(item, price)| match price {
+                Ok((token_id, price)) => {
+                    let owner_id = self.nft_token(token_id.to_owned()).unwrap().owner_id;
+                    self.record_revenue(RevenueKind::Renewal, price);
+                    self.accrue_referral(referrer.to_owned(), price, RevenueKind::Renewal);
+                    self.log_renewal(&token_id, &owner_id, &payer);
+                    self.internal_extend(token_id.to_owned(), owner_id, item.years * ONE_YEAR_MILLISECOND, price, None);
+                    BatchResult { name: token_id, success: true, charged: U128::from(price), error: None }
+                }
+                Err(err) => BatchResult::failed(item.name, err),
+            })
+            .collect();
+        if deposited > total {
+            Promise::new(payer).transfer(deposited - total);
+        }
+        Ok(results)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Registers up to `MAX_BATCH_SIZE` names paid by one deposit.
+    #[payable]
+    pub fn register_batch(&mut self, items: Vec<BatchRegisterItem>, referrer: Option<AccountId>) -> PromiseOrValue<Vec<BatchResult>> {
+        Self::require_unpaused();
+        assert!(!items.is_empty() && items.len() <= MAX_BATCH_SIZE, "Batch must have 1 to {} names", MAX_BATCH_SIZE);
+        let payer = env::predecessor_account_id();
+        let deposited = env::attached_deposit();
+        let referrer = self.valid_referrer(referrer, &payer);
+        if self.usd_pricing.is_some() {
+            Self::require_batch_gas();
+            self.check_batch_storage(&payer, items.len(), deposited).unwrap_or_else(|err| panic!("{}", err));
+            return self.quote_near_price().then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(BATCH_CALLBACK_GAS)
+                    .register_batch_with_quote(items, payer, U128::from(deposited), referrer)
+            ).into()
+        }
+        let results = self.register_batch_with_price(items, payer, deposited, self.price_for_one_year, referrer)
+            .unwrap_or_else(|err| panic!("{}", err));
+        PromiseOrValue::Value(results)
+    }
+
+    /// Renews up to `MAX_BATCH_SIZE` names paid by one deposit.
+    #[payable]
+    pub fn extend_batch(&mut self, items: Vec<BatchExtendItem>, referrer: Option<AccountId>) -> PromiseOrValue<Vec<BatchResult>> {
+        Self::require_unpaused();
+        assert!(!items.is_empty() && items.len() <= MAX_BATCH_SIZE, "Batch must have 1 to {} names", MAX_BATCH_SIZE);
+        let payer = env::predecessor_account_id();
+        let deposited = env::attached_deposit();
+        let referrer = self.valid_referrer(referrer, &payer);
+        if self.usd_pricing.is_some() {
+            Self::require_batch_gas();
+            return self.quote_near_price().then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(BATCH_CALLBACK_GAS)
+                    .extend_batch_with_quote(items, payer, U128::from(deposited), referrer)
+            ).into()
+        }
+        let results = self.extend_batch_with_price(items, payer, deposited, self.price_for_one_year, referrer)
+            .unwrap_or_else(|err| panic!("{}", err));
+        PromiseOrValue::Value(results)
+    }
+
+    /// Runs the batch at the quoted price. Every failure is checked before
+    /// any change, so nothing panics here and the deposit is returned whole,
+    /// without a refund in the treasury ledger as nothing was charged.
+    #[private]
+    pub fn register_batch_with_quote(
+        &mut self,
+        items: Vec<BatchRegisterItem>,
+        payer: AccountId,
+        deposited: U128,
+        referrer: Option<AccountId>,
+        #[callback_result] price_data: Result<PriceData, PromiseError>
+    ) -> Vec<BatchResult> {
+        let base_price = self.near_price_from_quote(price_data);
+        self.register_batch_with_price(items, payer.to_owned(), deposited.0, base_price, referrer)
+            .unwrap_or_else(|err| {
+                env::log_str(&err);
+                Promise::new(payer).transfer(deposited.0);
+                vec![]
+            })
+    }
+
+    /// Same as `register_batch_with_quote` for renewals.
+    #[private]
+    pub fn extend_batch_with_quote(
+        &mut self,
+        items: Vec<BatchExtendItem>,
+        payer: AccountId,
+        deposited: U128,
+        referrer: Option<AccountId>,
+        #[callback_result] price_data: Result<PriceData, PromiseError>
+    ) -> Vec<BatchResult> {
+        let base_price = self.near_price_from_quote(price_data);
+        self.extend_batch_with_price(items, payer.to_owned(), deposited.0, base_price, referrer)
+            .unwrap_or_else(|err| {
+                env::log_str(&err);
+                Promise::new(payer).transfer(deposited.0);
+                vec![]
+            })
+    }
+}
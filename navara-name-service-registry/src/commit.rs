@@ -0,0 +1,108 @@
+use near_contract_standards::non_fungible_token::refund_deposit;
+use near_sdk::json_types::Base58CryptoHash;
+
+use crate::*;
+
+pub const MIN_COMMITMENT_AGE: u64 = 60_000;
+pub const MAX_COMMITMENT_AGE: u64 = 86_400_000;
+const DEFAULT_PRUNE_LIMIT: u64 = 50;
+
+/// A commitment and the account that paid for its storage.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Commitment {
+    pub account_id: AccountId,
+    pub committed_at: u64,
+}
+
+impl Contract {
+    fn commitment_hash(&self, name: &TokenId, owner_id: &AccountId, salt: &str) -> CryptoHash {
+        env::sha256_array(format!("{}:{}:{}", name, owner_id, salt).as_bytes())
+    }
+
+    /// Checks that `name` was committed for `owner_id` with `salt` long
+    /// enough ago, returning the commitment to consume.
+    pub fn check_commitment(&self, name: &TokenId, owner_id: &AccountId, salt: &str) -> Result<CryptoHash, String> {
+        let commitment = self.commitment_hash(name, owner_id, salt);
+        let committed_at = self.commitments.get(&commitment).ok_or_else(|| "Commitment not found".to_owned())?.committed_at;
+        let now = env::block_timestamp_ms();
+        if committed_at + MIN_COMMITMENT_AGE > now {
+            return Err("Commitment too new".to_owned());
+        }
+        if committed_at + MAX_COMMITMENT_AGE < now {
+            return Err("Commitment expired".to_owned());
+        }
+        Ok(commitment)
+    }
+
+    /// Deletes `commitment` and refunds its storage to the account that made it.
+    pub fn internal_remove_commitment(&mut self, commitment: &CryptoHash) -> Option<Commitment> {
+        let initial_storage_usage = env::storage_usage();
+        let removed = self.commitments.remove(commitment)?;
+        let storage_released = initial_storage_usage - env::storage_usage();
+        Promise::new(removed.account_id.to_owned()).transfer(Balance::from(storage_released) * env::storage_byte_cost());
+        Some(removed)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Hash to pass to `commit` before revealing `name` with `reveal_register`.
+    pub fn make_commitment(&self, name: TokenId, owner_id: AccountId, salt: String) -> Base58CryptoHash {
+        let name = self.assert_valid_name(&name);
+        self.commitment_hash(&name, &owner_id, &salt).into()
+    }
+
+    /// Stores `commitment`. Its storage is paid by the attached deposit and
+    /// refunded once it is revealed or pruned.
+    #[payable]
+    pub fn commit(&mut self, commitment: Base58CryptoHash) {
+        let commitment = CryptoHash::from(commitment);
+        let now = env::block_timestamp_ms();
+        if let Some(existing) = self.commitments.get(&commitment) {
+            assert!(existing.committed_at + MAX_COMMITMENT_AGE < now, "Commitment exists");
+            self.internal_remove_commitment(&commitment);
+        }
+        let initial_storage_usage = env::storage_usage();
+        self.commitments.insert(&commitment, &Commitment {
+            account_id: env::predecessor_account_id(),
+            committed_at: now,
+        });
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+    }
+
+    pub fn commitment_timestamp(&self, commitment: Base58CryptoHash) -> Option<U64> {
+        self.commitments.get(&CryptoHash::from(commitment)).map(|commitment| U64::from(commitment.committed_at))
+    }
+
+    /// Deletes up to `limit` expired commitments and refunds their storage.
+    /// Anyone can call it, returns how many were deleted.
+    pub fn prune_commitments(&mut self, limit: Option<u64>) -> u64 {
+        let now = env::block_timestamp_ms();
+        let expired: Vec<CryptoHash> = self.commitments
+            .iter()
+            .filter(|(_, commitment)| commitment.committed_at + MAX_COMMITMENT_AGE < now)
+            .map(|(hash, _)| hash)
+            .take(limit.unwrap_or(DEFAULT_PRUNE_LIMIT) as usize)
+            .collect();
+        for commitment in expired.iter() {
+            self.internal_remove_commitment(commitment);
+        }
+        expired.len() as u64
+    }
+
+    /// The only public way to register a name: `register` would let anyone
+    /// front-run a pending reveal.
+    #[payable]
+    pub fn reveal_register(
+        &mut self,
+        name: TokenId,
+        owner_id: AccountId,
+        salt: String,
+        referrer: Option<AccountId>
+    ) -> Promise {
+        let token_id = self.assert_valid_name(&name);
+        let commitment = self.check_commitment(&token_id, &owner_id, &salt).unwrap_or_else(|err| panic!("{}", err));
+        self.internal_remove_commitment(&commitment);
+        self.register(token_id, owner_id, referrer)
+    }
+}
@@ -1,3 +1,5 @@
+use near_sdk::json_types::U128;
+
 use crate::*;
 
 #[near_bindgen]
@@ -10,10 +12,11 @@ impl NonFungibleTokenCore for Contract {
         approval_id: Option<u64>,
         memo: Option<String>,
     ) {
+        Self::require_unpaused();
         self.asset_name_expired(&token_id);
         let token = self.nft_token(token_id.to_owned()).unwrap();
         if self.is_default_name(&token) {
-            self.remove_default()
+            self.internal_remove_default(&token.owner_id)
         }
         self.tokens.nft_transfer(receiver_id, token_id, approval_id, memo)
     }
@@ -27,12 +30,54 @@ impl NonFungibleTokenCore for Contract {
         memo: Option<String>,
         msg: String,
     ) -> PromiseOrValue<bool> {
+        Self::require_unpaused();
         self.asset_name_expired(&token_id);
         self.tokens.nft_transfer_call(receiver_id, token_id, approval_id, memo, msg)
     }
 
     fn nft_token(&self, token_id: TokenId) -> Option<Token> {
-        self.tokens.nft_token(token_id)
+        self.tokens.nft_token(token_id).map(|token| self.with_expiry(token))
+    }
+}
+
+impl Contract {
+    /// Fills `expires_at` of the token metadata with the name expiry, in
+    /// milliseconds, so that wallets can hide or flag expired names, and
+    /// `media` with the artwork of the name.
+    pub fn with_expiry(&self, mut token: Token) -> Token {
+        let expired_date = self.expired_date_of(&token.token_id);
+        if let Some(metadata) = token.metadata.as_mut() {
+            metadata.expires_at = expired_date.map(|expired_date| expired_date.to_string());
+            metadata.media = Some(name_svg_data_url(&token.token_id, expired_date));
+        }
+        token
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenEnumeration for Contract {
+    fn nft_total_supply(&self) -> U128 {
+        self.tokens.nft_total_supply()
+    }
+
+    fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        self.tokens
+            .nft_tokens(from_index, limit)
+            .into_iter()
+            .map(|token| self.with_expiry(token))
+            .collect()
+    }
+
+    fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        self.tokens.nft_supply_for_owner(account_id)
+    }
+
+    fn nft_tokens_for_owner(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        self.tokens
+            .nft_tokens_for_owner(account_id, from_index, limit)
+            .into_iter()
+            .map(|token| self.with_expiry(token))
+            .collect()
     }
 }
 
@@ -48,7 +93,7 @@ impl NonFungibleTokenResolver for Contract {
     ) -> bool {
         let token = self.nft_token(token_id.to_owned()).unwrap();
         if self.is_default_name(&token) {
-            self.remove_default()
+            self.internal_remove_default(&token.owner_id)
         }
         self.tokens.nft_resolve_transfer(
             previous_owner_id,
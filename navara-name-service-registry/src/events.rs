@@ -0,0 +1,100 @@
+use near_sdk::json_types::U128;
+
+use crate::*;
+
+pub const NNS_STANDARD_NAME: &str = "nns";
+pub const NNS_EVENT_VERSION: &str = "1.0.0";
+
+/// Registry events logged as NEP-297 `EVENT_JSON`, so that an indexer can
+/// rebuild names, expiries, primary names and resolvers from receipts.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum NnsEvent {
+    NameRegister(Vec<NameRegisterData>),
+    NameRenew(Vec<NameRenewData>),
+    ExpiryChange(Vec<ExpiryChangeData>),
+    PrimaryNameChange(Vec<PrimaryNameChangeData>),
+    ResolverDeploy(Vec<ResolverData>),
+    OwnershipSync(Vec<ResolverData>),
+    ResolverUpgrade(Vec<ResolverData>),
+    SubnameCreate(Vec<SubnameData>),
+    SubnameRevoke(Vec<SubnameData>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+struct NnsEventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a NnsEvent,
+}
+
+impl NnsEvent {
+    pub fn emit(self) {
+        let log = NnsEventLog {
+            standard: NNS_STANDARD_NAME,
+            version: NNS_EVENT_VERSION,
+            event: &self,
+        };
+        env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(&log).unwrap()));
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameRegisterData {
+    pub token_id: TokenId,
+    pub owner_id: AccountId,
+    pub expired_date: U64,
+    pub charged: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ft_token_id: Option<AccountId>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameRenewData {
+    pub token_id: TokenId,
+    pub owner_id: AccountId,
+    pub expired_date: U64,
+    pub charged: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ft_token_id: Option<AccountId>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ExpiryChangeData {
+    pub token_id: TokenId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_expired_date: Option<U64>,
+    pub new_expired_date: U64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PrimaryNameChangeData {
+    pub account_id: AccountId,
+    pub token_id: Option<TokenId>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverData {
+    pub token_id: TokenId,
+    pub resolver_id: AccountId,
+    pub owner_id: AccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SubnameData {
+    pub token_id: TokenId,
+    pub parent_id: TokenId,
+    pub owner_id: AccountId,
+}
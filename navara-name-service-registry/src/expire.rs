@@ -1,11 +1,32 @@
-use near_sdk::json_types::U64;
+use near_sdk::assert_one_yocto;
+use near_sdk::json_types::U128;
 
 use crate::*;
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum NameState {
+    Active,
+    Grace,
+    Redemption,
+    Available,
+}
+
 impl Contract {
 
+    /// Expiry of `token_id`. The expiry of a subname is capped by the current
+    /// expiry of its parent, so that renewing the parent renews its subnames.
+    pub fn expired_date_of(&self, token_id: &TokenId) -> Option<u64> {
+        let expired_date = self.name_expired_date.get(token_id)?;
+        match self.parent_name.get(token_id) {
+            Some(parent_id) => Some(expired_date.min(self.name_expired_date.get(&parent_id).unwrap_or(0))),
+            None => Some(expired_date),
+        }
+    }
+
     pub fn is_name_expired(&self, token_id: &TokenId) -> bool {
-        let name_expire_date = self.name_expired_date.get(token_id).unwrap();
+        let name_expire_date = self.expired_date_of(token_id).unwrap();
         name_expire_date < env::block_timestamp_ms()
     }
 
@@ -14,30 +35,173 @@ impl Contract {
     }
 
     pub fn update_expired_date(&mut self, token_id: &TokenId, expired_date: &u64) {
-        assert!(expired_date.to_owned() > env::block_timestamp_ms(), "Invalid expired date");
-        self.name_expired_date.insert(token_id, expired_date);
+        assert!(*expired_date > env::block_timestamp_ms(), "Invalid expired date");
+        let old_expired_date = self.name_expired_date.insert(token_id, expired_date);
+        NnsEvent::ExpiryChange(vec![ExpiryChangeData {
+            token_id: token_id.to_owned(),
+            old_expired_date: old_expired_date.map(U64::from),
+            new_expired_date: U64::from(*expired_date),
+        }]).emit();
+    }
+
+    pub fn name_state_of(&self, token_id: &TokenId) -> NameState {
+        let now = env::block_timestamp_ms();
+        match self.expired_date_of(token_id) {
+            Some(expired_date) if now <= expired_date => NameState::Active,
+            Some(expired_date) if now <= expired_date + self.grace_period => NameState::Grace,
+            Some(expired_date) if now <= expired_date + self.grace_period + self.redemption_period => NameState::Redemption,
+            _ => NameState::Available,
+        }
+    }
+
+    pub fn internal_extend(
+        &mut self,
+        token_id: TokenId,
+        owner_id: AccountId,
+        duration: u64,
+        charged: Balance,
+        ft_token_id: Option<AccountId>
+    ) -> u64 {
+        let name_expired_date = self.name_expired_date.get(&token_id).unwrap_or(env::block_timestamp_ms());
+        let new_expired_date = name_expired_date + duration;
+        self.update_expired_date(&token_id, &new_expired_date);
+        NnsEvent::NameRenew(vec![NameRenewData {
+            token_id,
+            owner_id,
+            expired_date: U64::from(new_expired_date),
+            charged: U128::from(charged),
+            ft_token_id,
+        }]).emit();
+        new_expired_date
+    }
+
+    /// Anyone can renew a name unless its owner opted out of renewals paid
+    /// by other accounts.
+    pub fn check_renewer(&self, token: &Token, payer: &AccountId) -> Result<(), String> {
+        if token.owner_id != *payer && self.gift_renewal_opt_outs.contains(&token.owner_id) {
+            return Err("Owner does not accept renewals by others".to_owned());
+        }
+        Ok(())
+    }
+
+    pub fn log_renewal(&self, token_id: &TokenId, owner_id: &AccountId, payer: &AccountId) {
+        if owner_id != payer {
+            env::log_str(&format!("{} renewed {} for {}", payer, token_id, owner_id));
+        }
+    }
+
+    /// Charges `payer` for extending `token_id`, refunding what is not needed.
+    pub fn extend_with_price(
+        &mut self,
+        token_id: TokenId,
+        payer: AccountId,
+        deposited: Balance,
+        base_price: Balance,
+        referrer: Option<AccountId>
+    ) -> Result<Balance, String> {
+        let token = self.nft_token(token_id.to_owned()).ok_or_else(|| "Name not found".to_owned())?;
+        self.check_renewer(&token, &payer)?;
+        let fee = match self.name_state_of(&token_id) {
+            NameState::Active | NameState::Grace => 0,
+            NameState::Redemption => self.redemption_fee,
+            NameState::Available => return Err("Name expired".to_owned()),
+        };
+        if deposited < fee {
+            return Err("Deposit not enough for redemption fee".to_owned());
+        }
+
+        let (duration, charged) = self.purchased_duration(&token_id, deposited - fee, base_price);
+        if duration == 0 {
+            return Err("Deposit at least one year".to_owned());
+        }
+        if self.name_expired_date.get(&token_id).unwrap() + duration <= env::block_timestamp_ms() {
+            return Err("Invalid expired date".to_owned());
+        }
+        let charged = charged + fee;
+        self.log_renewal(&token_id, &token.owner_id, &payer);
+        if deposited > charged {
+            Promise::new(payer).transfer(deposited - charged);
+        }
+        self.record_revenue(RevenueKind::Renewal, charged);
+        self.accrue_referral(referrer, charged, RevenueKind::Renewal);
+        self.internal_extend(token_id, token.owner_id, duration, charged, None);
+        Ok(charged)
+    }
+
+    /// Time at which a previously registered name became available again.
+    pub fn released_at(&self, token_id: &TokenId) -> Option<u64> {
+        self.expired_date_of(token_id)
+            .map(|expired_date| expired_date + self.grace_period + self.redemption_period)
     }
 }
 
 #[near_bindgen]
 impl Contract {
     #[payable]
-    pub fn extend(&mut self, token_id: TokenId) {
-        self.token_owner_only(&token_id);
-        
-        let name_expired_date = self.name_expired_date.get(&token_id).unwrap_or(env::block_timestamp_ms());
-        assert!(name_expired_date >= env::block_timestamp_ms(), "Name expired");
+    pub fn extend(&mut self, token_id: TokenId, referrer: Option<AccountId>) -> PromiseOrValue<U128> {
+        Self::require_unpaused();
+        let token_id = self.assert_valid_token_id(&token_id);
+        let token = self.nft_token(token_id.to_owned()).expect("Name not found");
+        assert!(!self.is_subname(&token_id), "Subnames follow their parent expiry");
+        assert_ne!(self.name_state_of(&token_id), NameState::Available, "Name expired");
 
+        let payer = env::predecessor_account_id();
+        self.check_renewer(&token, &payer).unwrap_or_else(|err| panic!("{}", err));
         let deposited = env::attached_deposit();
-        let years_extended: u64 = (deposited / self.price_for_one_year).try_into().unwrap();
-        let new_expired_date = name_expired_date + (years_extended * ONE_YEAR_MILLISECOND);
-        self.update_expired_date(&token_id, &new_expired_date);
+        let referrer = self.valid_referrer(referrer, &payer);
+        if self.usd_pricing.is_some() {
+            return self.quote_near_price().then(
+                Self::ext(env::current_account_id()).extend_with_quote(token_id, payer, U128::from(deposited), referrer)
+            ).into()
+        }
+        let charged = self.extend_with_price(token_id, payer, deposited, self.price_for_one_year, referrer)
+            .unwrap_or_else(|err| panic!("{}", err));
+        PromiseOrValue::Value(U128::from(charged))
     }
 
     pub fn expired_date(&self, token_id: TokenId) -> Option<U64> {
-        if let Some(expired_date) = self.name_expired_date.get(&token_id) {
+        if let Some(expired_date) = self.expired_date_of(&token_id) {
             return Some(U64::from(expired_date))
         }
         None
     }
-}
\ No newline at end of file
+
+    pub fn name_state(&self, token_id: TokenId) -> NameState {
+        self.name_state_of(&token_id)
+    }
+
+    #[payable]
+    pub fn set_grace_period(&mut self, grace_period: U64, redemption_period: U64, redemption_fee: U128) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        self.grace_period = grace_period.into();
+        self.redemption_period = redemption_period.into();
+        self.redemption_fee = redemption_fee.into();
+    }
+
+    pub fn grace_period(&self) -> U64 {
+        U64::from(self.grace_period)
+    }
+
+    pub fn redemption_period(&self) -> U64 {
+        U64::from(self.redemption_period)
+    }
+
+    pub fn redemption_fee(&self) -> U128 {
+        U128::from(self.redemption_fee)
+    }
+
+    /// Lets the caller refuse renewals of its names paid by other accounts.
+    pub fn set_accept_gift_renewals(&mut self, accept: bool) {
+        let account_id = env::predecessor_account_id();
+        if accept {
+            self.gift_renewal_opt_outs.remove(&account_id);
+        } else {
+            self.gift_renewal_opt_outs.insert(&account_id);
+        }
+    }
+
+    pub fn accepts_gift_renewals(&self, account_id: AccountId) -> bool {
+        !self.gift_renewal_opt_outs.contains(&account_id)
+    }
+}
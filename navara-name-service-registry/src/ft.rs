@@ -0,0 +1,195 @@
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::{ext_contract, json_types::U128, assert_one_yocto};
+
+use crate::*;
+
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+/// `msg` of an `ft_transfer_call` paying for a name, e.g.
+/// `{"action": "register", "name": "alice", "owner_id": "alice.near", "years": 1, "salt": "..."}`.
+/// Registrations reveal a matured commitment, as `reveal_register` does.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum FtPaymentMessage {
+    Register { name: TokenId, owner_id: AccountId, years: u64, salt: String },
+    Extend { name: TokenId, years: u64 },
+}
+
+impl Contract {
+    /// Converts a yoctoNEAR amount into `ft_token_id` units, where the token
+    /// price configured by the owner buys one year of a base-priced name.
+    /// Rounds up so that token payers are never undercharged, `None` when
+    /// the token is not accepted or the amount cannot be converted.
+    pub fn ft_amount(&self, ft_token_id: &AccountId, amount: Balance) -> Option<Balance> {
+        let ft_price = self.ft_prices.get(ft_token_id)?;
+        let base_price = self.price_for_one_year;
+        if base_price == 0 {
+            return None;
+        }
+        ft_price
+            .checked_mul(amount)?
+            .checked_add(base_price - 1)
+            .map(|numerator| numerator / base_price)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn ft_register(
+        &mut self,
+        ft_token_id: &AccountId,
+        sender_id: &AccountId,
+        name: TokenId,
+        owner_id: AccountId,
+        years: u64,
+        salt: String,
+        amount: Balance
+    ) -> Result<Balance, String> {
+        let token_id = self.normalize_name(&name)?;
+        let commitment = self.check_commitment(&token_id, &owner_id, &salt)?;
+        if years == 0 {
+            return Err("Register at least one year".to_owned());
+        }
+        if self.name_state_of(&token_id) != NameState::Available {
+            return Err(format!("{} is not available", token_id));
+        }
+        if self.is_reserved(&token_id) {
+            return Err(format!("{} is reserved", token_id));
+        }
+        let price = self.yearly_price(&token_id) * years as Balance + self.premium_of(&token_id);
+        let charged = self.ft_amount(ft_token_id, price).ok_or_else(|| "Token price unavailable".to_owned())?;
+        if charged > amount {
+            return Err(format!("Requires {} tokens", charged));
+        }
+        self.internal_remove_commitment(&commitment);
+        let token_metadata = self.metadata_by_token(&token_id);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_register_name(token_id.to_owned(), owner_id, token_metadata, years * ONE_YEAR_MILLISECOND, charged, Some(ft_token_id.to_owned()));
+        self.internal_set_storage_payer(&token_id, sender_id);
+        // Storage is paid from the NEP-145 balance of the sender. Panicking
+        // here makes the token contract refund the whole transfer.
+        self.charge_storage(sender_id, env::storage_usage() - initial_storage_usage, 0);
+        Ok(charged)
+    }
+
+    fn ft_extend(
+        &mut self,
+        ft_token_id: &AccountId,
+        sender_id: &AccountId,
+        name: TokenId,
+        years: u64,
+        amount: Balance
+    ) -> Result<Balance, String> {
+        let name = self.normalize_name(&name)?;
+        if years == 0 {
+            return Err("Extend at least one year".to_owned());
+        }
+        let token = self.nft_token(name.to_owned()).ok_or_else(|| format!("{} is not registered", name))?;
+        self.check_renewer(&token, sender_id)?;
+        if self.is_subname(&name) {
+            return Err("Subnames follow their parent expiry".to_owned());
+        }
+        let fee = match self.name_state_of(&name) {
+            NameState::Active | NameState::Grace => 0,
+            NameState::Redemption => self.redemption_fee,
+            NameState::Available => return Err("Name expired".to_owned()),
+        };
+        let price = self.yearly_price(&name) * years as Balance + fee;
+        let charged = self.ft_amount(ft_token_id, price).ok_or_else(|| "Token price unavailable".to_owned())?;
+        if charged > amount {
+            return Err(format!("Requires {} tokens", charged));
+        }
+        if self.name_expired_date.get(&name).unwrap() + years * ONE_YEAR_MILLISECOND <= env::block_timestamp_ms() {
+            return Err("Invalid expired date".to_owned());
+        }
+        self.log_renewal(&name, &token.owner_id, sender_id);
+        self.internal_extend(name, token.owner_id, years * ONE_YEAR_MILLISECOND, charged, Some(ft_token_id.to_owned()));
+        Ok(charged)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// Registers or extends a name paid with an allow-listed NEP-141 token.
+    /// Whatever is not charged is returned to the sender by the token contract.
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        Self::require_unpaused();
+        let ft_token_id = env::predecessor_account_id();
+        assert!(self.ft_prices.get(&ft_token_id).is_some(), "Token not accepted");
+        let message: FtPaymentMessage = serde_json::from_str(&msg).expect("Invalid message");
+        let amount = u128::from(amount);
+        let result = match message {
+            FtPaymentMessage::Register { name, owner_id, years, salt } => self.ft_register(&ft_token_id, &sender_id, name, owner_id, years, salt, amount),
+            FtPaymentMessage::Extend { name, years } => self.ft_extend(&ft_token_id, &sender_id, name, years, amount),
+        };
+        match result {
+            Ok(charged) => {
+                let balance = self.ft_balances.get(&ft_token_id).unwrap_or(0);
+                self.ft_balances.insert(&ft_token_id, &(balance + charged));
+                PromiseOrValue::Value(U128::from(amount - charged))
+            }
+            Err(err) => {
+                env::log_str(&err);
+                PromiseOrValue::Value(U128::from(amount))
+            }
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn set_ft_price(&mut self, ft_token_id: AccountId, price: U128) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        self.ft_prices.insert(&ft_token_id, &u128::from(price));
+    }
+
+    #[payable]
+    pub fn remove_ft_price(&mut self, ft_token_id: AccountId) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        self.ft_prices.remove(&ft_token_id);
+    }
+
+    pub fn ft_prices(&self) -> Vec<(AccountId, U128)> {
+        self.ft_prices
+            .iter()
+            .map(|(ft_token_id, price)| (ft_token_id, U128::from(price)))
+            .collect()
+    }
+
+    /// Token amount needed to register `token_id` for `years` with `ft_token_id`.
+    pub fn ft_price_for_name(&self, ft_token_id: AccountId, token_id: TokenId, years: u64) -> Option<U128> {
+        let price = self.yearly_price(&token_id) * years as Balance + self.premium_of(&token_id);
+        self.ft_amount(&ft_token_id, price).map(U128::from)
+    }
+
+    pub fn ft_balance(&self, ft_token_id: AccountId) -> U128 {
+        U128::from(self.ft_balances.get(&ft_token_id).unwrap_or(0))
+    }
+
+    #[payable]
+    pub fn withdraw_ft(&mut self, ft_token_id: AccountId, amount: U128, beneficiary: AccountId) -> Promise {
+        assert_one_yocto();
+        Self::require_role(&Role::Treasurer);
+        let balance = self.ft_balances.get(&ft_token_id).unwrap_or(0);
+        assert!(amount.0 <= balance, "Not enough token balance");
+        self.ft_balances.insert(&ft_token_id, &(balance - amount.0));
+        ext_ft::ext(ft_token_id.to_owned())
+            .with_attached_deposit(1)
+            .with_static_gas(GAS)
+            .ft_transfer(beneficiary, amount, None)
+            .then(Self::ext(env::current_account_id()).on_ft_withdraw(ft_token_id, amount))
+    }
+
+    #[private]
+    pub fn on_ft_withdraw(&mut self, ft_token_id: AccountId, amount: U128) {
+        if let PromiseResult::Failed = env::promise_result(0) {
+            let balance = self.ft_balances.get(&ft_token_id).unwrap_or(0);
+            self.ft_balances.insert(&ft_token_id, &(balance + amount.0));
+        }
+    }
+}
@@ -4,45 +4,128 @@ use near_contract_standards::non_fungible_token::metadata::{
 use near_contract_standards::non_fungible_token::NonFungibleToken;
 use near_contract_standards::non_fungible_token::{Token, TokenId};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, UnorderedMap};
+use near_sdk::collections::{LazyOption, LookupMap, TreeMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::{Base64VecU8, U64};
 use near_sdk::{
-    env, near_bindgen, require, AccountId, BorshStorageKey, PanicOnDefault, Promise, PromiseOrValue, Balance, PromiseResult, serde_json, Gas
+    assert_one_yocto, env, near_bindgen, require, AccountId, BorshStorageKey, PanicOnDefault, Promise, PromiseOrValue, Balance, PromiseResult, serde_json, Gas, CryptoHash
 };
 
-use near_sdk_contract_tools::owner::OwnerExternal;
-use near_sdk_contract_tools::{owner::Owner, Owner};
+// The exported owner and pause methods call these traits.
+#[cfg(target_arch = "wasm32")]
+use near_sdk_contract_tools::{owner::OwnerExternal, pause::PauseExternal};
+use near_sdk_contract_tools::{owner::Owner, pause::Pause, rbac::Rbac, Owner, Pause, Rbac};
 
 const GAS: Gas = Gas(50_000_000_000_000);
+const MIGRATE_GAS: Gas = Gas(50_000_000_000_000);
 
 use near_sdk::env::{STORAGE_PRICE_PER_BYTE};
 use near_sdk::serde::{Deserialize, Serialize};
 
 use near_contract_standards::non_fungible_token::core::{NonFungibleTokenCore, NonFungibleTokenResolver};
-
-const RESOLVER_WASM_CODE: &[u8] = include_bytes!("../../out/resolver.wasm");
+use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
 
 const DEFAULT_ONE_NEAR_YOCTO: Balance = 1_000_000_000_000_000_000_000_000;
 const ONE_YEAR_MILLISECOND: u64 = 31556952000;
-const DEFAULT_REGISTER_GAS_DEPOSIT: Balance = DEFAULT_ONE_NEAR_YOCTO / 2;
+const ONE_DAY_MILLISECOND: u64 = 86_400_000;
+const DEFAULT_GRACE_PERIOD: u64 = 30 * ONE_DAY_MILLISECOND;
+const DEFAULT_PREMIUM_DURATION: u64 = 21 * ONE_DAY_MILLISECOND;
 
 pub use crate::expire::*;
+#[allow(unused_imports)]
 pub use crate::register::*;
 pub use crate::resolver::*;
+#[allow(unused_imports)]
 pub use crate::core_impl::*;
+#[allow(unused_imports)]
 pub use crate::price::*;
+pub use crate::name::*;
+pub use crate::commit::*;
+pub use crate::events::*;
+pub use crate::treasury::*;
+pub use crate::ft::*;
+pub use crate::oracle::*;
+pub use crate::referral::*;
+pub use crate::roles::*;
+pub use crate::batch::*;
+pub use crate::svg::*;
+pub use crate::payout::*;
+pub use crate::offers::*;
+pub use crate::resolver_code::*;
 
 mod expire;
 mod register;
 mod resolver;
 mod core_impl;
 mod price;
+mod name;
+mod commit;
+mod subname;
+mod events;
+mod treasury;
+mod ft;
+mod oracle;
+mod referral;
+mod reserved;
+mod pause;
+mod roles;
+mod storage;
+mod batch;
+mod reap;
+mod svg;
+mod payout;
+mod offers;
+mod resolver_code;
 
 #[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault, Owner)]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault, Owner, Pause, Rbac)]
+#[rbac(roles = "Role")]
 pub struct Contract {
     tokens: NonFungibleToken,
     metadata: LazyOption<NFTContractMetadata>,
     price_for_one_year: Balance,
+    price_by_length: TreeMap<u32, Balance>,
+    premium_start: Balance,
+    premium_duration: u64,
+    charge_pro_rata: bool,
+    usd_pricing: Option<UsdPricing>,
+    name_expired_date: UnorderedMap<TokenId, u64>,
+    grace_period: u64,
+    redemption_period: u64,
+    redemption_fee: Balance,
+    default_name: UnorderedMap<AccountId, TokenId>,
+    parent_name: UnorderedMap<TokenId, TokenId>,
+    subnames: UnorderedMap<TokenId, Vec<TokenId>>,
+    commitments: UnorderedMap<CryptoHash, Commitment>,
+    total_revenue: Revenue,
+    revenue_by_period: UnorderedMap<u64, Revenue>,
+    ft_prices: UnorderedMap<AccountId, Balance>,
+    ft_balances: UnorderedMap<AccountId, Balance>,
+    referral_fee: u16,
+    referrals: UnorderedMap<AccountId, ReferralStats>,
+    referral_liability: Balance,
+    reserved_names: UnorderedSet<TokenId>,
+    storage_balances: LookupMap<AccountId, Balance>,
+    total_storage_balance: Balance,
+    storage_payers: LookupMap<TokenId, AccountId>,
+    gift_renewal_opt_outs: UnorderedSet<AccountId>,
+    reap_reward: u16,
+    royalties: Royalties,
+    registrants: UnorderedMap<TokenId, AccountId>,
+    offers: UnorderedMap<TokenId, Vec<Offer>>,
+    total_offers: Balance,
+    resolver_versions: TreeMap<u32, ResolverVersion>,
+    resolver_codes: LookupMap<u32, Vec<u8>>,
+    resolver_version_by_name: UnorderedMap<TokenId, u32>,
+    resolver_count_by_version: LookupMap<u32, u64>
+}
+
+/// Layout of the first release, read by `migrate`.
+#[derive(BorshDeserialize, BorshSerialize)]
+struct OldContract {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    price_for_one_year: Balance,
+    /// Dropped, storage is charged at cost.
     fee_register: Balance,
     name_expired_date: UnorderedMap<TokenId, u64>,
     default_name: UnorderedMap<AccountId, TokenId>
@@ -58,7 +141,77 @@ enum StorageKey {
     Enumeration,
     Approval,
     NameExpiredDate,
-    DefaultName
+    DefaultName,
+    PriceByLength,
+    Commitments,
+    ParentName,
+    Subnames,
+    RevenueByPeriod,
+    FtPrices,
+    FtBalances,
+    Referrals,
+    ReservedNames,
+    StorageBalances,
+    GiftRenewalOptOuts,
+    Registrants,
+    Offers,
+    ResolverVersions,
+    ResolverCodes,
+    ResolverVersionByName,
+    StoragePayers,
+    ResolverCountByVersion
+}
+
+impl Contract {
+    /// State of a registry holding these collections, with defaults for
+    /// every other setting.
+    fn with_state(
+        tokens: NonFungibleToken,
+        metadata: LazyOption<NFTContractMetadata>,
+        price_for_one_year: Balance,
+        name_expired_date: UnorderedMap<TokenId, u64>,
+        default_name: UnorderedMap<AccountId, TokenId>
+    ) -> Self {
+        Self {
+            tokens,
+            metadata,
+            name_expired_date,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            redemption_period: 0,
+            redemption_fee: 0,
+            default_name,
+            parent_name: UnorderedMap::new(StorageKey::ParentName),
+            subnames: UnorderedMap::new(StorageKey::Subnames),
+            commitments: UnorderedMap::new(StorageKey::Commitments),
+            total_revenue: Revenue::default(),
+            revenue_by_period: UnorderedMap::new(StorageKey::RevenueByPeriod),
+            ft_prices: UnorderedMap::new(StorageKey::FtPrices),
+            ft_balances: UnorderedMap::new(StorageKey::FtBalances),
+            referral_fee: 0,
+            referrals: UnorderedMap::new(StorageKey::Referrals),
+            referral_liability: 0,
+            reserved_names: UnorderedSet::new(StorageKey::ReservedNames),
+            storage_balances: LookupMap::new(StorageKey::StorageBalances),
+            total_storage_balance: 0,
+            storage_payers: LookupMap::new(StorageKey::StoragePayers),
+            gift_renewal_opt_outs: UnorderedSet::new(StorageKey::GiftRenewalOptOuts),
+            reap_reward: 0,
+            royalties: Royalties::default(),
+            registrants: UnorderedMap::new(StorageKey::Registrants),
+            offers: UnorderedMap::new(StorageKey::Offers),
+            total_offers: 0,
+            resolver_versions: TreeMap::new(StorageKey::ResolverVersions),
+            resolver_codes: LookupMap::new(StorageKey::ResolverCodes),
+            resolver_version_by_name: UnorderedMap::new(StorageKey::ResolverVersionByName),
+            resolver_count_by_version: LookupMap::new(StorageKey::ResolverCountByVersion),
+            price_for_one_year,
+            price_by_length: TreeMap::new(StorageKey::PriceByLength),
+            premium_start: 0,
+            premium_duration: DEFAULT_PREMIUM_DURATION,
+            charge_pro_rata: false,
+            usd_pricing: None
+        }
+    }
 }
 
 #[near_bindgen]
@@ -81,8 +234,7 @@ impl Contract {
                 reference: None,
                 reference_hash: None,
             },
-            DEFAULT_ONE_NEAR_YOCTO,
-            DEFAULT_REGISTER_GAS_DEPOSIT
+            DEFAULT_ONE_NEAR_YOCTO
         )
     }
 
@@ -90,39 +242,55 @@ impl Contract {
     pub fn new(
         owner_id: AccountId, 
         metadata: NFTContractMetadata, 
-        price_for_one_year: Balance, 
-        fee_register: Balance
+        price_for_one_year: Balance
     ) -> Self {
         require!(!env::state_exists(), "Already initialized");
         metadata.assert_valid();
-        let mut contract = Self {
-            tokens: NonFungibleToken::new(
+        let mut contract = Self::with_state(
+            NonFungibleToken::new(
                 StorageKey::NonFungibleToken,
                 owner_id.to_owned(),
                 Some(StorageKey::TokenMetadata),
                 Some(StorageKey::Enumeration),
                 Some(StorageKey::Approval),
             ),
-            metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
-            name_expired_date: UnorderedMap::new(StorageKey::NameExpiredDate),
-            default_name: UnorderedMap::new(StorageKey::DefaultName),
+            LazyOption::new(StorageKey::Metadata, Some(&metadata)),
             price_for_one_year,
-            fee_register
-        };
+            UnorderedMap::new(StorageKey::NameExpiredDate),
+            UnorderedMap::new(StorageKey::DefaultName),
+        );
         Owner::init(&mut contract, &owner_id);
+        contract.grant_all_roles(&owner_id);
         contract
     }
 
+    /// Deploys new registry code and migrates the state in the same
+    /// transaction, so a failed migration reverts the deployment.
+    #[payable]
+    pub fn upgrade(&mut self, code: Base64VecU8) -> Promise {
+        assert_one_yocto();
+        Self::require_role(&Role::Upgrader);
+        Promise::new(env::current_account_id())
+            .deploy_contract(code.into())
+            .function_call("migrate".to_string(), vec![], 0, MIGRATE_GAS)
+    }
+
+    /// Reads the state in the current layout, or in the layout of the first
+    /// release. The first release had no roles, so the owner of a migrated
+    /// state gets every role.
     #[private]
     #[init(ignore_state)]
-    pub fn migrate(fee_register: Balance) -> Self {
-        // retrieve the current state from the contract
-        let old_state = env::state_read().expect("failed");
-        Self {
-            fee_register,
-            ..old_state
-            
+    pub fn migrate() -> Self {
+        let state = env::storage_read(b"STATE").expect("Not initialized");
+        if let Ok(contract) = Self::try_from_slice(&state) {
+            return contract;
+        }
+        let old = OldContract::try_from_slice(&state).expect("Unknown state");
+        let mut contract = Self::with_state(old.tokens, old.metadata, old.price_for_one_year, old.name_expired_date, old.default_name);
+        if let Some(owner_id) = Self::slot_owner().read() {
+            contract.grant_all_roles(&owner_id);
         }
+        contract
     }
 
     fn token_owner_only(&self, token_id: &TokenId) -> Token {
@@ -132,6 +300,8 @@ impl Contract {
         token
     }
 
+    /// Returns the deposit of a failed `register_name`. Its revenue was never
+    /// recorded, so this is not a refund in the treasury ledger.
     #[private]
     pub fn failure_resolve(&mut self, signer: AccountId, deposited: Balance) {
         if let PromiseResult::Failed = env::promise_result(0) {
@@ -142,7 +312,7 @@ impl Contract {
 
 // near_contract_standards::impl_non_fungible_token_core!(Contract, tokens);
 near_contract_standards::impl_non_fungible_token_approval!(Contract, tokens);
-near_contract_standards::impl_non_fungible_token_enumeration!(Contract, tokens);
+// near_contract_standards::impl_non_fungible_token_enumeration!(Contract, tokens);
 
 #[near_bindgen]
 impl NonFungibleTokenMetadataProvider for Contract {
@@ -152,14 +322,18 @@ impl NonFungibleTokenMetadataProvider for Contract {
 }
 
 #[cfg(all(test, not(target_arch = "wasm32")))]
+#[allow(clippy::useless_conversion)]
 mod tests {
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::testing_env;
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::json_types::{U128, U64};
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseError};
     use std::collections::HashMap;
 
     use super::*;
 
-    const MINT_STORAGE_COST: u128 = 5870000000000000000000;
+    const MINT_STORAGE_COST: u128 = 15_000_000_000_000_000_000_000;
 
     fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
@@ -170,21 +344,11 @@ mod tests {
         builder
     }
 
-    fn sample_token_metadata() -> TokenMetadata {
-        TokenMetadata {
-            title: Some("Olympus Mons".into()),
-            description: Some("The tallest mountain in the charted solar system".into()),
-            media: None,
-            media_hash: None,
-            copies: Some(1u64),
-            issued_at: None,
-            expires_at: None,
-            starts_at: None,
-            updated_at: None,
-            extra: None,
-            reference: None,
-            reference_hash: None,
-        }
+    /// Stores a commitment made at time 0, matured once the block time
+    /// reaches `MIN_COMMITMENT_AGE`.
+    fn commit_name(contract: &mut Contract, name: &str, owner_id: AccountId, salt: &str) {
+        let commitment = contract.make_commitment(name.to_string(), owner_id.to_owned(), salt.to_string());
+        contract.commitments.insert(&commitment.into(), &Commitment { account_id: owner_id, committed_at: 0 });
     }
 
     #[test]
@@ -196,27 +360,31 @@ mod tests {
         assert_eq!(contract.nft_token("1".to_string()), None);
     }
 
-    #[test]
-    #[should_panic(expected = "The contract is not initialized")]
-
     #[test]
     fn test_mint() {
         let mut context = get_context(accounts(0));
         testing_env!(context.build());
         let mut contract = Contract::new_default_meta(accounts(0).into());
 
+        let token_id = "0".to_string();
+        let price = contract.yearly_price(&token_id);
         testing_env!(context
             .storage_usage(env::storage_usage())
-            .attached_deposit(MINT_STORAGE_COST)
+            .attached_deposit(2 * price + MINT_STORAGE_COST)
             .predecessor_account_id(accounts(0))
             .build());
 
-        let token_id = "0".to_string();
-        contract.register(token_id.clone(), accounts(0));
+        contract.register(token_id.clone(), accounts(0), None);
+        // `register` mints through a `register_name` call on the registry itself.
+        contract.register_name(token_id.clone(), accounts(0), 2 * ONE_YEAR_MILLISECOND, None, accounts(0), U128(2 * price));
         let token = contract.nft_token(token_id.to_owned()).unwrap();
         assert_eq!(token.token_id, token_id.to_owned());
         assert_eq!(token.owner_id, accounts(0));
-        assert_eq!(token.metadata.unwrap(), sample_token_metadata());
+        let metadata = token.metadata.unwrap();
+        assert_eq!(metadata.title, contract.metadata_by_token(&token_id).title);
+        assert_eq!(metadata.expires_at, Some((2 * ONE_YEAR_MILLISECOND).to_string()));
+        let tokens = contract.nft_tokens_for_owner(accounts(0), None, None);
+        assert_eq!(tokens[0].metadata.as_ref().unwrap().expires_at, metadata.expires_at);
         assert_eq!(token.approved_account_ids.unwrap(), HashMap::new());
     }
 
@@ -232,7 +400,7 @@ mod tests {
             .predecessor_account_id(accounts(0))
             .build());
         let token_id = "0".to_string();
-        contract.register_name(token_id.clone(), accounts(0), sample_token_metadata(), 2);
+        contract.register_name(token_id.clone(), accounts(0), 2 * ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
 
         testing_env!(context
             .storage_usage(env::storage_usage())
@@ -250,7 +418,7 @@ mod tests {
         if let Some(token) = contract.nft_token(token_id.clone()) {
             assert_eq!(token.token_id, token_id);
             assert_eq!(token.owner_id, accounts(1));
-            assert_eq!(token.metadata.unwrap(), sample_token_metadata());
+            assert_eq!(token.metadata.unwrap().title, contract.metadata_by_token(&token_id).title);
             assert_eq!(token.approved_account_ids.unwrap(), HashMap::new());
         } else {
             panic!("token not correctly created, or not found by nft_token");
@@ -263,13 +431,15 @@ mod tests {
         testing_env!(context.build());
         let mut contract = Contract::new_default_meta(accounts(0).into());
 
+        let token_id = "0".to_string();
+        let price = contract.yearly_price(&token_id);
         testing_env!(context
             .storage_usage(env::storage_usage())
-            .attached_deposit(MINT_STORAGE_COST)
+            .attached_deposit(2 * price + MINT_STORAGE_COST)
             .predecessor_account_id(accounts(0))
             .build());
-        let token_id = "0".to_string();
-        contract.register(token_id.clone(), accounts(0));
+        contract.register(token_id.clone(), accounts(0), None);
+        contract.register_name(token_id.clone(), accounts(0), 2 * ONE_YEAR_MILLISECOND, None, accounts(0), U128(2 * price));
 
         // alice approves bob
         testing_env!(context
@@ -300,7 +470,7 @@ mod tests {
             .predecessor_account_id(accounts(0))
             .build());
         let token_id = "0".to_string();
-        contract.register_name(token_id.clone(), accounts(0), sample_token_metadata(), 2);
+        contract.register_name(token_id.clone(), accounts(0), 2 * ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
 
         // alice approves bob
         testing_env!(context
@@ -338,7 +508,7 @@ mod tests {
             .predecessor_account_id(accounts(0))
             .build());
         let token_id = "0".to_string();
-        contract.register_name(token_id.clone(), accounts(0), sample_token_metadata(), 2);
+        contract.register_name(token_id.clone(), accounts(0), 2 * ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
 
         // alice approves bob
         testing_env!(context
@@ -363,4 +533,1491 @@ mod tests {
             .build());
         assert!(!contract.nft_is_approved(token_id.clone(), accounts(1), Some(1)));
     }
+
+    #[test]
+    fn test_normalize_name() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0));
+        assert_eq!(contract.normalize_name("  Alice ").unwrap(), "alice");
+        assert_eq!(contract.normalize_name("my-name_1").unwrap(), "my-name_1");
+        assert!(contract.normalize_name("").is_err());
+        assert!(contract.normalize_name("pay.alice").is_err());
+        assert!(contract.normalize_name("al ice").is_err());
+        assert!(contract.normalize_name("-alice").is_err());
+        assert!(contract.normalize_name("al--ice").is_err());
+        assert!(contract.normalize_name(&"a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Name must not contain '.'")]
+    fn test_register_invalid_name() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context
+            .attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.register("pay.alice".to_string(), accounts(0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Name is reserved")]
+    fn test_register_reserved_name() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.add_reserved_names(vec!["Navara".to_string()]);
+        assert!(contract.is_reserved_name("navara".to_string()));
+
+        testing_env!(context.attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.register("navara".to_string(), accounts(1), None);
+    }
+
+    #[test]
+    fn test_storage_management() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        let min = Contract::min_storage_balance();
+        assert_eq!(contract.storage_balance_bounds().min, U128(min));
+
+        let longest_account: AccountId = "a".repeat(64).parse().unwrap();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(min).build());
+        let initial_storage_usage = env::storage_usage();
+        contract.storage_deposit(Some(longest_account), Some(true));
+        assert_eq!(env::storage_usage() - initial_storage_usage, crate::storage::ACCOUNT_STORAGE_BYTES);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(MINT_STORAGE_COST + min).build());
+        contract.storage_deposit(None, None);
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().total, U128(MINT_STORAGE_COST + min));
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().available, U128(MINT_STORAGE_COST));
+
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .build());
+        let initial_storage_usage = env::storage_usage();
+        contract.register_name("alice".to_string(), accounts(1), ONE_YEAR_MILLISECOND, None, accounts(1), U128(0));
+        let storage_cost = Balance::from(env::storage_usage() - initial_storage_usage) * env::storage_byte_cost();
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().available, U128(MINT_STORAGE_COST - storage_cost));
+
+        // The primary name is paid from the balance and credited back.
+        testing_env!(context.predecessor_account_id(accounts(1)).storage_usage(env::storage_usage()).build());
+        contract.set_default("alice".to_string());
+        assert!(contract.storage_balance_of(accounts(1)).unwrap().available.0 < MINT_STORAGE_COST - storage_cost);
+        contract.remove_default();
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().available, U128(MINT_STORAGE_COST - storage_cost));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.storage_withdraw(None);
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().available, U128(0));
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().total, U128(min));
+        assert!(contract.storage_unregister(None));
+        assert!(contract.storage_balance_of(accounts(1)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "The attached deposit is less than the minimum storage balance")]
+    fn test_storage_deposit_below_min() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(Contract::min_storage_balance() - 1).build());
+        contract.storage_deposit(None, Some(true));
+    }
+
+    #[test]
+    fn test_reap_credits_storage_payer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        let min = Contract::min_storage_balance();
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(MINT_STORAGE_COST + min).build());
+        contract.storage_deposit(None, None);
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .build());
+        contract.register_name("alice".to_string(), accounts(2), ONE_YEAR_MILLISECOND, None, accounts(1), U128(0));
+        let available = contract.storage_balance_of(accounts(1)).unwrap().available.0;
+
+        let reapable_at = ONE_YEAR_MILLISECOND + DEFAULT_GRACE_PERIOD + DEFAULT_PREMIUM_DURATION;
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(reapable_at * 1_000_000)
+            .storage_usage(env::storage_usage())
+            .build());
+        let initial_storage_usage = env::storage_usage();
+        assert_eq!(contract.reap_expired(vec!["alice".to_string()]), U128(0));
+        let storage_released = Balance::from(initial_storage_usage - env::storage_usage()) * env::storage_byte_cost();
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().available, U128(available + storage_released));
+    }
+
+    #[test]
+    fn test_register_batch() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        commit_name(&mut contract, "alice", accounts(1), "salt");
+        commit_name(&mut contract, "bob", accounts(1), "salt");
+
+        let item = |name: &str, years| BatchRegisterItem { name: name.to_string(), owner_id: accounts(1), years, salt: "salt".to_string() };
+        let items = vec![item("alice", 1), item("bob", 2), item("Alice", 1), item("pay.alice", 1), item("carol", 1)];
+        testing_env!(context
+            .block_timestamp(MIN_COMMITMENT_AGE * 1_000_000)
+            .storage_usage(env::storage_usage())
+            .attached_deposit(3 * DEFAULT_ONE_NEAR_YOCTO + 2 * MINT_STORAGE_COST)
+            .build());
+        let results = match contract.register_batch(items, None) {
+            PromiseOrValue::Value(results) => results,
+            PromiseOrValue::Promise(_) => panic!("Expected results"),
+        };
+        assert_eq!(results.iter().filter(|result| result.success).count(), 2);
+        assert_eq!(results[1].charged, U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+        assert_eq!(results[2].error, Some("Duplicate name".to_string()));
+        assert_eq!(results[3].error, Some("Name must not contain '.'".to_string()));
+        assert_eq!(results[4].error, Some("Commitment not found".to_string()));
+        assert_eq!(contract.nft_token("bob".to_string()).unwrap().owner_id, accounts(1));
+        assert_eq!(contract.revenue().registration, U128(3 * DEFAULT_ONE_NEAR_YOCTO));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO).build());
+        let items = vec![
+            BatchExtendItem { name: "alice".to_string(), years: 1 },
+            BatchExtendItem { name: "Bob".to_string(), years: 1 },
+            BatchExtendItem { name: "carol".to_string(), years: 1 },
+            BatchExtendItem { name: "ALICE".to_string(), years: 1 },
+        ];
+        let results = match contract.extend_batch(items, None) {
+            PromiseOrValue::Value(results) => results,
+            PromiseOrValue::Promise(_) => panic!("Expected results"),
+        };
+        assert_eq!(results[1].name, "bob".to_string());
+        assert_eq!(results[2].error, Some("Name not found".to_string()));
+        assert_eq!(results[3].error, Some("Duplicate name".to_string()));
+        assert_eq!(u64::from(contract.expired_date("bob".to_string()).unwrap()), MIN_COMMITMENT_AGE + 3 * ONE_YEAR_MILLISECOND);
+    }
+
+    #[test]
+    #[should_panic(expected = "Deposit at least")]
+    fn test_register_batch_not_enough_deposit() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        commit_name(&mut contract, "alice", accounts(1), "salt");
+        commit_name(&mut contract, "bob", accounts(1), "salt");
+
+        let items = vec![
+            BatchRegisterItem { name: "alice".to_string(), owner_id: accounts(1), years: 1, salt: "salt".to_string() },
+            BatchRegisterItem { name: "bob".to_string(), owner_id: accounts(1), years: 1, salt: "salt".to_string() },
+        ];
+        testing_env!(context.block_timestamp(MIN_COMMITMENT_AGE * 1_000_000).attached_deposit(DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.register_batch(items, None);
+    }
+
+    #[test]
+    fn test_roles() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        assert_eq!(contract.roles_of(accounts(0)).len(), ROLES.len());
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.grant_role(Role::PricingManager, accounts(1));
+        assert_eq!(contract.roles_of(accounts(1)), vec![Role::PricingManager]);
+        assert_eq!(contract.role_members(Role::PricingManager), vec![accounts(0), accounts(1)]);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_price(U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+        assert_eq!(contract.price_per_year(), U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.revoke_role(Role::PricingManager, accounts(1));
+        assert!(contract.roles_of(accounts(1)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized role")]
+    fn test_withdraw_requires_treasurer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.grant_role(Role::PricingManager, accounts(1));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.withdraw(U128(1), accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized role")]
+    fn test_pause_requires_moderator() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.grant_role(Role::Moderator, accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.pause();
+        assert!(Contract::is_paused());
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.unpause();
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized role")]
+    fn test_upgrade_requires_upgrader() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.upgrade(vec![0u8].into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Disallowed while contract is paused")]
+    fn test_register_while_paused() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.pause();
+        assert!(Contract::is_paused());
+        assert!(get_logs()[0].contains("x-paus"));
+
+        testing_env!(context.attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.register("alice".to_string(), accounts(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Disallowed while contract is paused")]
+    fn test_create_subname_while_paused() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.register_name("alice".to_string(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        testing_env!(context.attached_deposit(1).build());
+        contract.pause();
+
+        testing_env!(context.attached_deposit(DEFAULT_ONE_NEAR_YOCTO / 10).build());
+        contract.create_subname("alice".to_string(), "pay".to_string(), accounts(1), None);
+    }
+
+    #[test]
+    fn test_migrate_from_old_contract() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.register_name("alice".to_string(), accounts(1), ONE_YEAR_MILLISECOND, None, accounts(1), U128(0));
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(MINT_STORAGE_COST).build());
+        contract.set_default("alice".to_string());
+
+        env::state_write(&OldContract {
+            tokens: contract.tokens,
+            metadata: contract.metadata,
+            price_for_one_year: 2 * DEFAULT_ONE_NEAR_YOCTO,
+            fee_register: DEFAULT_ONE_NEAR_YOCTO / 2,
+            name_expired_date: contract.name_expired_date,
+            default_name: contract.default_name,
+        });
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let contract = Contract::migrate();
+        assert_eq!(contract.nft_token("alice".to_string()).unwrap().owner_id, accounts(1));
+        assert_eq!(contract.expired_date("alice".to_string()), Some(U64(ONE_YEAR_MILLISECOND)));
+        assert_eq!(contract.default_name(accounts(1)), Some("alice".to_string()));
+        assert_eq!(contract.price_per_year(), U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+        assert_eq!(contract.grace_period, DEFAULT_GRACE_PERIOD);
+        assert!(contract.price_tiers().is_empty());
+        assert_eq!(contract.roles_of(accounts(0)).len(), ROLES.len());
+
+        // Migrating again keeps the current layout and the roles.
+        let mut contract = contract;
+        testing_env!(context.attached_deposit(1).build());
+        contract.revoke_role(Role::Upgrader, accounts(0));
+        env::state_write(&contract);
+        let contract = Contract::migrate();
+        assert_eq!(contract.price_per_year(), U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+        assert!(!contract.roles_of(accounts(0)).contains(&Role::Upgrader));
+    }
+
+    #[test]
+    fn test_pause_resolvers() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name("alice".to_string(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        contract.register_name("bob".to_string(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(DEFAULT_ONE_NEAR_YOCTO / 10).build());
+        contract.create_subname("alice".to_string(), "pay".to_string(), accounts(1), None);
+
+        testing_env!(context.attached_deposit(1).build());
+        assert_eq!(contract.pause_resolvers(None, None), 2);
+        assert_eq!(contract.pause_resolvers(Some(U128(1)), Some(1)), 1);
+        assert_eq!(contract.unpause_resolvers(Some(U128(2)), None), 0);
+    }
+
+    #[test]
+    fn test_unpause() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.pause();
+        contract.unpause();
+        assert!(!Contract::is_paused());
+
+        testing_env!(context.attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.register("alice".to_string(), accounts(1), None);
+    }
+
+    #[test]
+    fn test_admin_register() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.add_reserved_names(vec!["near".to_string(), "admin".to_string()]);
+        assert_eq!(contract.reserved_names(None, None).len(), 2);
+
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        let token = contract.admin_register("near".to_string(), accounts(1), U64(ONE_YEAR_MILLISECOND));
+        assert_eq!(token.owner_id, accounts(1));
+        assert_eq!(contract.expired_date("near".to_string()), Some(U64(ONE_YEAR_MILLISECOND)));
+        assert_eq!(contract.name_state("near".to_string()), NameState::Active);
+
+        contract.remove_reserved_names(vec!["admin".to_string()]);
+        assert!(!contract.is_reserved_name("admin".to_string()));
+    }
+
+    #[test]
+    fn test_price_tiers() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_price_for_length(1, U128(10 * DEFAULT_ONE_NEAR_YOCTO));
+        contract.set_price_for_length(4, U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+        contract.set_price_for_length(10, U128(DEFAULT_ONE_NEAR_YOCTO / 2));
+
+        assert_eq!(contract.price_tiers().len(), 3);
+        assert_eq!(contract.yearly_price(&"abc".to_string()), 10 * DEFAULT_ONE_NEAR_YOCTO);
+        assert_eq!(contract.yearly_price(&"alice".to_string()), 2 * DEFAULT_ONE_NEAR_YOCTO);
+        assert_eq!(contract.yearly_price(&"averylongname".to_string()), DEFAULT_ONE_NEAR_YOCTO / 2);
+
+        contract.remove_price_for_length(1);
+        assert_eq!(contract.yearly_price(&"abc".to_string()), DEFAULT_ONE_NEAR_YOCTO);
+    }
+
+    #[test]
+    fn test_commit_reveal() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let salt = "secret".to_string();
+        let commitment = contract.make_commitment("Alice".to_string(), accounts(1), salt.to_owned());
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.commit(commitment);
+        assert_eq!(contract.commitment_timestamp(commitment), Some(U64(0)));
+
+        testing_env!(context
+            .block_timestamp(MIN_COMMITMENT_AGE * 1_000_000)
+            .attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO)
+            .build());
+        contract.reveal_register("alice".to_string(), accounts(1), salt, None);
+        assert_eq!(contract.commitment_timestamp(commitment), None);
+    }
+
+    #[test]
+    fn test_prune_commitments() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let commitment = contract.make_commitment("alice".to_string(), accounts(1), "secret".to_string());
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.commit(commitment);
+        assert_eq!(contract.prune_commitments(None), 0);
+
+        testing_env!(context
+            .block_timestamp((MAX_COMMITMENT_AGE + 1) * 1_000_000)
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(0)
+            .build());
+        assert_eq!(contract.prune_commitments(None), 1);
+        assert_eq!(contract.commitment_timestamp(commitment), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Commitment too new")]
+    fn test_reveal_too_early() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let salt = "secret".to_string();
+        let commitment = contract.make_commitment("alice".to_string(), accounts(1), salt.to_owned());
+        testing_env!(context.attached_deposit(MINT_STORAGE_COST).build());
+        contract.commit(commitment);
+
+        testing_env!(context.attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.reveal_register("alice".to_string(), accounts(1), salt, None);
+    }
+
+    #[test]
+    fn test_name_state() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_grace_period(U64(ONE_DAY_MILLISECOND), U64(ONE_DAY_MILLISECOND), U128(DEFAULT_ONE_NEAR_YOCTO));
+
+        let token_id = "alice".to_string();
+        assert_eq!(contract.name_state(token_id.to_owned()), NameState::Available);
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        assert_eq!(contract.name_state(token_id.to_owned()), NameState::Active);
+
+        let expired_date = u64::from(contract.expired_date(token_id.to_owned()).unwrap());
+        testing_env!(context.block_timestamp((expired_date + 1) * 1_000_000).build());
+        assert_eq!(contract.name_state(token_id.to_owned()), NameState::Grace);
+
+        testing_env!(context.block_timestamp((expired_date + ONE_DAY_MILLISECOND + 1) * 1_000_000).build());
+        assert_eq!(contract.name_state(token_id.to_owned()), NameState::Redemption);
+
+        testing_env!(context.block_timestamp((expired_date + 2 * ONE_DAY_MILLISECOND + 1) * 1_000_000).build());
+        assert_eq!(contract.name_state(token_id), NameState::Available);
+    }
+
+    #[test]
+    fn test_payout() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_royalties(Royalties { treasury: 250, registrant: 500 });
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(2), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(1).build());
+        let payout = contract.nft_transfer_payout(accounts(3), token_id.to_owned(), None, None, U128(10_000), Some(3));
+        assert_eq!(payout.payout, HashMap::from([(accounts(0), U128(250)), (accounts(2), U128(9_750))]));
+        assert_eq!(contract.nft_token(token_id.to_owned()).unwrap().owner_id, accounts(3));
+
+        let payout = contract.nft_payout(token_id, U128(10_000), None);
+        assert_eq!(payout.payout, HashMap::from([(accounts(0), U128(250)), (accounts(2), U128(500)), (accounts(3), U128(9_250))]));
+    }
+
+    #[test]
+    fn test_accept_offer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(2), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO + MINT_STORAGE_COST)
+            .build());
+        let offer = contract.make_offer("Alice".to_string(), U128(2 * DEFAULT_ONE_NEAR_YOCTO), U64(ONE_DAY_MILLISECOND));
+        assert!(offer.storage_deposit.0 > 0);
+        assert_eq!(contract.offers_for_name(token_id.to_owned()), vec![offer]);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(1).build());
+        let payout = contract.accept_offer("ALICE".to_string(), accounts(1));
+        assert_eq!(payout.payout, HashMap::from([(accounts(2), U128(2 * DEFAULT_ONE_NEAR_YOCTO))]));
+        assert_eq!(contract.nft_token(token_id.to_owned()).unwrap().owner_id, accounts(1));
+        assert!(contract.offers_for_name(token_id).is_empty());
+        assert_eq!(contract.total_offers, 0);
+    }
+
+    #[test]
+    fn test_offer_evicts_lowest() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(2), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        let offer_amount = |i: usize| (i as Balance + 1) * DEFAULT_ONE_NEAR_YOCTO / 10;
+        for i in 0..MAX_OFFERS_PER_NAME {
+            let buyer_id: AccountId = format!("buyer{}.near", i).parse().unwrap();
+            testing_env!(context
+                .storage_usage(env::storage_usage())
+                .predecessor_account_id(buyer_id)
+                .attached_deposit(offer_amount(i) + MINT_STORAGE_COST)
+                .build());
+            contract.make_offer(token_id.to_owned(), U128(offer_amount(i)), U64(ONE_DAY_MILLISECOND));
+        }
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(DEFAULT_ONE_NEAR_YOCTO)
+            .build());
+        contract.make_offer(token_id.to_owned(), U128(offer_amount(0) + 1), U64(ONE_DAY_MILLISECOND));
+        let offers = contract.offers_for_name(token_id.to_owned());
+        assert_eq!(offers.len(), MAX_OFFERS_PER_NAME);
+        assert!(offers.iter().all(|offer| offer.buyer_id.as_str() != "buyer0.near"));
+        assert!(offers.iter().any(|offer| offer.buyer_id == accounts(1)));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(0).build());
+        contract.withdraw_offer("Alice".to_string());
+        assert!(contract.offers_for_name(token_id).iter().all(|offer| offer.buyer_id != accounts(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer at least its storage cost")]
+    fn test_offer_below_storage_cost() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(2), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(MINT_STORAGE_COST)
+            .build());
+        contract.make_offer(token_id, U128(1), U64(ONE_DAY_MILLISECOND));
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer expired")]
+    fn test_accept_expired_offer() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(2), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(DEFAULT_ONE_NEAR_YOCTO)
+            .build());
+        contract.make_offer(token_id.to_owned(), U128(DEFAULT_ONE_NEAR_YOCTO / 2), U64(ONE_DAY_MILLISECOND));
+
+        testing_env!(context
+            .block_timestamp(ONE_DAY_MILLISECOND * 1_000_000)
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        contract.accept_offer(token_id, accounts(1));
+    }
+
+    #[test]
+    fn test_upload_resolver_code() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        assert_eq!(contract.latest_resolver_version(), EMBEDDED_RESOLVER_VERSION);
+        assert_eq!(contract.resolver_code(None).0, EMBEDDED_RESOLVER_VERSION);
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.grant_role(Role::Upgrader, accounts(1));
+        let code = b"resolver v2".to_vec();
+        let hash = near_sdk::json_types::Base58CryptoHash::from(env::sha256_array(&code));
+        testing_env!(context.storage_usage(env::storage_usage()).predecessor_account_id(accounts(1)).attached_deposit(MINT_STORAGE_COST).build());
+        let resolver_version = contract.upload_resolver_code(2, hash, code.into());
+        assert_eq!(resolver_version.uploader_id, accounts(1));
+        let code = b"resolver v3".to_vec();
+        let hash = near_sdk::json_types::Base58CryptoHash::from(env::sha256_array(&code));
+        testing_env!(context.storage_usage(env::storage_usage()).predecessor_account_id(accounts(0)).build());
+        contract.upload_resolver_code(3, hash, code.to_owned().into());
+
+        assert_eq!(contract.latest_resolver_version(), 3);
+        assert_eq!(contract.resolver_versions().len(), 2);
+        assert_eq!(contract.resolver_code(None), (3, code));
+        assert_eq!(contract.resolver_code(Some(2)).1, b"resolver v2".to_vec());
+
+        // Removed by another upgrader, refunded to the uploader.
+        testing_env!(context.attached_deposit(1).build());
+        contract.remove_resolver_code(2);
+        let refunds: Vec<AccountId> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .map(|receipt| receipt.receiver_id)
+            .collect();
+        assert_eq!(refunds.last(), Some(&accounts(1)));
+        contract.remove_resolver_code(3);
+        assert_eq!(contract.latest_resolver_version(), EMBEDDED_RESOLVER_VERSION);
+    }
+
+    #[test]
+    #[should_panic(expected = "Resolvers still run this version")]
+    fn test_remove_resolver_code_in_use() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let code = b"resolver v2".to_vec();
+        let hash = near_sdk::json_types::Base58CryptoHash::from(env::sha256_array(&code));
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.upload_resolver_code(2, hash, code.into());
+        contract.register_name("alice".to_string(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(
+            context.attached_deposit(0).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        contract.on_resolver_setup("alice".to_string(), accounts(0), 0, 2);
+        assert_eq!(contract.resolvers_on_version(2), 1);
+        contract.internal_burn(&"alice".to_string());
+        assert_eq!(contract.resolvers_on_version(2), 0);
+
+        testing_env!(
+            context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        contract.register_name("bob".to_string(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        contract.on_resolver_setup("bob".to_string(), accounts(0), 0, 2);
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.remove_resolver_code(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Resolver already on a newer version")]
+    fn test_upgrade_resolver_to_older_version() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(
+            context.attached_deposit(0).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])]
+        );
+        contract.on_resolver_setup(token_id.clone(), accounts(0), 0, 2);
+        assert_eq!(contract.resolver_version_of(token_id.clone()), Some(2));
+        contract.upgrade_resolver(token_id, 1);
+    }
+
+    #[test]
+    fn test_setup_embedded_resolver() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(1), ONE_YEAR_MILLISECOND, None, accounts(1), U128(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(5 * DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.setup(token_id, None);
+        let deployed: Vec<usize> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::VmAction::DeployContract { code } => Some(code.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deployed, vec![contract.resolver_code(Some(EMBEDDED_RESOLVER_VERSION)).1.len()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Resolver predates versioning and cannot be upgraded")]
+    fn test_upgrade_legacy_resolver() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(1), ONE_YEAR_MILLISECOND, None, accounts(1), U128(0));
+
+        // Resolvers deployed before versioning have no recorded version.
+        assert_eq!(contract.resolver_version_of(token_id.clone()), None);
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.upgrade_resolver(token_id, EMBEDDED_RESOLVER_VERSION);
+    }
+
+    #[test]
+    fn test_embedded_resolver_is_upgradable() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0));
+        let (_, code) = contract.resolver_code(Some(EMBEDDED_RESOLVER_VERSION));
+        for method in [&b"upgrade"[..], b"on_upgrade", b"migrate", b"pause", b"unpause", b"version"] {
+            assert!(code.windows(method.len()).any(|name| name == method), "Embedded resolver has no {:?}", method);
+        }
+    }
+
+    #[test]
+    fn test_on_resolver_upgrade() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(1), ONE_YEAR_MILLISECOND, None, accounts(1), U128(0));
+
+        testing_env!(
+            context.attached_deposit(0).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(b"true".to_vec())]
+        );
+        assert!(contract.on_resolver_upgrade(token_id.clone(), accounts(1), 2, U128(10)));
+        assert_eq!(contract.resolver_version_of(token_id.clone()), Some(2));
+        assert_eq!(contract.resolvers_on_version(2), 1);
+        assert!(near_sdk::test_utils::get_created_receipts().is_empty());
+
+        for result in [PromiseResult::Failed, PromiseResult::Successful(b"false".to_vec())] {
+            testing_env!(
+                context.attached_deposit(0).build(),
+                near_sdk::VMConfig::test(),
+                near_sdk::RuntimeFeesConfig::test(),
+                Default::default(),
+                vec![result]
+            );
+            assert!(!contract.on_resolver_upgrade(token_id.clone(), accounts(1), 3, U128(10)));
+            assert_eq!(contract.resolver_version_of(token_id.clone()), Some(2));
+            let refunds: Vec<Balance> = near_sdk::test_utils::get_created_receipts()
+                .into_iter()
+                .filter(|receipt| receipt.receiver_id == accounts(1))
+                .flat_map(|receipt| receipt.actions)
+                .filter_map(|action| match action {
+                    near_sdk::mock::VmAction::Transfer { deposit } => Some(deposit),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(refunds, vec![10]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Hash does not match the code")]
+    fn test_upload_resolver_code_wrong_hash() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let hash = near_sdk::json_types::Base58CryptoHash::from(env::sha256_array(b"other"));
+        testing_env!(context.attached_deposit(MINT_STORAGE_COST).build());
+        contract.upload_resolver_code(2, hash, b"resolver v2".to_vec().into());
+    }
+
+    #[test]
+    fn test_nft_svg() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        assert_eq!(contract.nft_svg("alice".to_string()), None);
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name("alice".to_string(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        let svg = contract.nft_svg("alice".to_string()).unwrap();
+        assert!(svg.contains("alice.nns"));
+        assert!(svg.contains("expires 1971-01-01"));
+        assert!(svg.contains("#00b4db"));
+
+        let media = contract.nft_token("alice".to_string()).unwrap().metadata.unwrap().media.unwrap();
+        assert!(media.starts_with("data:image/svg+xml,%3Csvg"));
+        assert!(!media.contains('#'));
+    }
+
+    #[test]
+    fn test_name_svg_escapes_name() {
+        let svg = name_svg(&"<a href='x'>&\"</a>".to_string(), None);
+        assert!(svg.contains("&lt;a href=&apos;x&apos;&gt;&amp;&quot;&lt;/a&gt;.nns"));
+        assert!(!svg.contains("<a "));
+    }
+
+    #[test]
+    #[should_panic(expected = "Name not available")]
+    fn test_register_name_twice() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name("alice".to_string(), accounts(1), ONE_YEAR_MILLISECOND, None, accounts(1), U128(0));
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        contract.register_name("alice".to_string(), accounts(2), ONE_YEAR_MILLISECOND, None, accounts(2), U128(0));
+    }
+
+    #[test]
+    fn test_register_released_name() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(1), ONE_YEAR_MILLISECOND, None, accounts(1), U128(0));
+        testing_env!(context.predecessor_account_id(accounts(1)).storage_usage(env::storage_usage()).attached_deposit(MINT_STORAGE_COST).build());
+        contract.nft_approve(token_id.clone(), accounts(3), None);
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(DEFAULT_ONE_NEAR_YOCTO / 10).build());
+        contract.create_subname(token_id.clone(), "pay".to_string(), accounts(1), None);
+
+        let released_at = contract.released_at(&token_id).unwrap();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .block_timestamp((released_at + 1) * 1_000_000)
+            .storage_usage(env::storage_usage())
+            .attached_deposit(2 * MINT_STORAGE_COST)
+            .build());
+        contract.register_name(token_id.clone(), accounts(2), ONE_YEAR_MILLISECOND, None, accounts(2), U128(0));
+        let token = contract.nft_token(token_id.to_owned()).unwrap();
+        assert_eq!(token.owner_id, accounts(2));
+        assert!(token.approved_account_ids.unwrap().is_empty());
+        assert!(!contract.nft_is_approved(token_id.to_owned(), accounts(3), None));
+        assert!(contract.nft_token("pay.alice".to_string()).is_none());
+        assert!(contract.subnames_of(token_id).is_empty());
+        assert_eq!(contract.nft_supply_for_owner(accounts(1)), U128(0));
+    }
+
+    #[test]
+    fn test_reap_expired() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_reap_reward(5_000);
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        contract.set_default(token_id.to_owned());
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(0).build());
+        assert_eq!(contract.reap_expired(vec![token_id.to_owned()]), U128(0));
+        assert!(get_logs().contains(&"alice cannot be reaped".to_string()));
+
+        let released_at = contract.released_at(&token_id).unwrap();
+        testing_env!(context.block_timestamp((released_at + DEFAULT_PREMIUM_DURATION) * 1_000_000).build());
+        assert!(contract.reap_expired(vec!["Alice".to_string()]).0 > 0);
+        assert!(contract.nft_token(token_id.to_owned()).is_none());
+        assert_eq!(contract.expired_date(token_id), None);
+        assert_eq!(contract.default_name(accounts(0)), None);
+        assert_eq!(contract.nft_supply_for_owner(accounts(0)), U128(0));
+    }
+
+    #[test]
+    fn test_extend_in_redemption() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_grace_period(U64(ONE_DAY_MILLISECOND), U64(ONE_DAY_MILLISECOND), U128(DEFAULT_ONE_NEAR_YOCTO));
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        let expired_date = u64::from(contract.expired_date(token_id.to_owned()).unwrap());
+
+        testing_env!(context
+            .block_timestamp((expired_date + ONE_DAY_MILLISECOND + 1) * 1_000_000)
+            .attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO)
+            .build());
+        contract.extend(token_id.to_owned(), None);
+        assert_eq!(u64::from(contract.expired_date(token_id.to_owned()).unwrap()), expired_date + ONE_YEAR_MILLISECOND);
+        assert_eq!(contract.name_state(token_id), NameState::Active);
+    }
+
+    #[test]
+    #[should_panic(expected = "Name not available")]
+    fn test_register_in_grace() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        let expired_date = u64::from(contract.expired_date(token_id.to_owned()).unwrap());
+
+        testing_env!(context
+            .block_timestamp((expired_date + 1) * 1_000_000)
+            .attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.register(token_id, accounts(1), None);
+    }
+
+    #[test]
+    fn test_premium_decay() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_grace_period(U64(0), U64(0), U128(0));
+        contract.set_premium(U128(100 * DEFAULT_ONE_NEAR_YOCTO), 10);
+
+        let token_id = "alice".to_string();
+        assert_eq!(contract.premium(token_id.to_owned()), U128(0));
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        let expired_date = u64::from(contract.expired_date(token_id.to_owned()).unwrap());
+        assert_eq!(contract.premium(token_id.to_owned()), U128(0));
+
+        testing_env!(context.block_timestamp(expired_date * 1_000_000).build());
+        assert_eq!(contract.premium(token_id.to_owned()), U128(100 * DEFAULT_ONE_NEAR_YOCTO));
+
+        testing_env!(context.block_timestamp((expired_date + 5 * ONE_DAY_MILLISECOND) * 1_000_000).build());
+        assert_eq!(contract.premium(token_id.to_owned()), U128(50 * DEFAULT_ONE_NEAR_YOCTO));
+
+        testing_env!(context.block_timestamp((expired_date + 10 * ONE_DAY_MILLISECOND) * 1_000_000).build());
+        assert_eq!(contract.premium(token_id), U128(0));
+    }
+
+    #[test]
+    fn test_subname() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        let parent_id = "alice".to_string();
+        contract.register_name(parent_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        let parent_expired_date = contract.expired_date(parent_id.to_owned()).unwrap();
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(DEFAULT_ONE_NEAR_YOCTO / 10).build());
+        let token = contract.create_subname("Alice".to_string(), "Pay".to_string(), accounts(1), Some(U64(u64::MAX)));
+        assert_eq!(token.token_id, "pay.alice");
+        assert_eq!(token.owner_id, accounts(1));
+        assert_eq!(contract.expired_date("pay.alice".to_string()), Some(parent_expired_date));
+        assert_eq!(contract.parent_of("pay.alice".to_string()), Some(parent_id.to_owned()));
+        assert_eq!(contract.subnames_of(parent_id.to_owned()), vec!["pay.alice".to_string()]);
+
+        testing_env!(context.attached_deposit(DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.extend(parent_id.to_owned(), None);
+        let parent_expired_date = contract.expired_date(parent_id.to_owned()).unwrap();
+        assert_eq!(contract.expired_date("pay.alice".to_string()), Some(parent_expired_date));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.revoke_subname(" Pay.Alice".to_string());
+        assert!(contract.nft_token("pay.alice".to_string()).is_none());
+        assert_eq!(contract.expired_date("pay.alice".to_string()), None);
+        assert!(contract.subnames_of(parent_id).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only token owner")]
+    fn test_create_subname_not_parent_owner() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name("alice".to_string(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.create_subname("alice".to_string(), "pay".to_string(), accounts(1), None);
+    }
+
+    #[test]
+    fn test_registry_events() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name("alice".to_string(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        contract.set_default("alice".to_string());
+        contract.remove_default();
+
+        let logs = get_logs();
+        assert_eq!(
+            logs[0],
+            format!(
+                r#"EVENT_JSON:{{"standard":"nns","version":"1.0.0","event":"expiry_change","data":[{{"token_id":"alice","new_expired_date":"{}"}}]}}"#,
+                ONE_YEAR_MILLISECOND
+            )
+        );
+        assert!(logs.iter().any(|log| log.contains(r#""event":"name_register""#)));
+        assert!(logs.iter().any(|log| log.contains(r#""event":"primary_name_change","data":[{"account_id":"alice","token_id":"alice"}]"#)));
+        assert!(logs.iter().any(|log| log.contains(r#""event":"primary_name_change","data":[{"account_id":"alice","token_id":null}]"#)));
+    }
+
+    #[test]
+    fn test_failed_register_is_not_revenue() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(
+            context.build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.failure_resolve(accounts(1), 2 * DEFAULT_ONE_NEAR_YOCTO);
+        assert_eq!(contract.revenue(), Revenue::default());
+    }
+
+    #[test]
+    fn test_withdraw() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * DEFAULT_ONE_NEAR_YOCTO + 2 * MINT_STORAGE_COST).build());
+        contract.register_name("alice".to_string(), accounts(0), 2 * ONE_YEAR_MILLISECOND, None, accounts(0), U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+        assert_eq!(contract.revenue().registration, U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+        assert_eq!(contract.revenue_by_period(None, None), vec![(U64(0), contract.revenue())]);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .account_balance(10 * DEFAULT_ONE_NEAR_YOCTO)
+            .attached_deposit(1)
+            .build());
+        let available = contract.available_balance();
+        assert_eq!(available.0, env::account_balance() - contract.locked_balance());
+        contract.withdraw(U128(DEFAULT_ONE_NEAR_YOCTO), accounts(1));
+        assert_eq!(contract.revenue().withdrawn, U128(DEFAULT_ONE_NEAR_YOCTO));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough available balance")]
+    fn test_withdraw_storage_balance() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .account_balance(10 * DEFAULT_ONE_NEAR_YOCTO)
+            .attached_deposit(1)
+            .build());
+        contract.withdraw(U128(10 * DEFAULT_ONE_NEAR_YOCTO), accounts(1));
+    }
+
+    #[test]
+    fn test_extend_refunds_remainder() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(context.attached_deposit(DEFAULT_ONE_NEAR_YOCTO * 17 / 10).build());
+        assert!(matches!(contract.extend(token_id.to_owned(), None), PromiseOrValue::Value(U128(DEFAULT_ONE_NEAR_YOCTO))));
+        assert_eq!(u64::from(contract.expired_date(token_id.to_owned()).unwrap()), 2 * ONE_YEAR_MILLISECOND);
+        assert_eq!(contract.revenue().renewal, U128(DEFAULT_ONE_NEAR_YOCTO));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_charge_pro_rata(true);
+        testing_env!(context.attached_deposit(DEFAULT_ONE_NEAR_YOCTO / 2).build());
+        assert!(matches!(contract.extend(token_id.to_owned(), None), PromiseOrValue::Value(U128(amount)) if amount == DEFAULT_ONE_NEAR_YOCTO / 2));
+        assert_eq!(u64::from(contract.expired_date(token_id).unwrap()), 2 * ONE_YEAR_MILLISECOND + ONE_YEAR_MILLISECOND / 2);
+    }
+
+    #[test]
+    fn test_purchased_duration_large_deposit() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_charge_pro_rata(true);
+
+        let token_id = "alice".to_string();
+        let amount = 20_000 * DEFAULT_ONE_NEAR_YOCTO + DEFAULT_ONE_NEAR_YOCTO / 2;
+        assert_eq!(
+            contract.purchased_duration(&token_id, amount, DEFAULT_ONE_NEAR_YOCTO),
+            (20_000 * ONE_YEAR_MILLISECOND + ONE_YEAR_MILLISECOND / 2, amount)
+        );
+        let price = 200_000 * DEFAULT_ONE_NEAR_YOCTO;
+        let (duration, charged) = contract.purchased_duration(&token_id, 3 * price / 2, price);
+        assert_eq!(charged, 3 * price / 2);
+        assert!(duration.abs_diff(3 * ONE_YEAR_MILLISECOND / 2) <= 1);
+    }
+
+    #[test]
+    fn test_register_pro_rata() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_charge_pro_rata(true);
+
+        let storage_reserve = Balance::from(MAX_NAME_STORAGE_BYTES) * env::storage_byte_cost();
+        let deposited = 3 * DEFAULT_ONE_NEAR_YOCTO / 2 + storage_reserve;
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(0).build());
+        assert!(contract.register_with_price("alice".to_string(), accounts(1), accounts(1), deposited, DEFAULT_ONE_NEAR_YOCTO, None).is_ok());
+        assert!(get_logs().contains(&format!("Charged {} yoctoNEAR to register alice", 3 * DEFAULT_ONE_NEAR_YOCTO / 2)));
+
+        // The reserve pays the storage without a NEP-145 balance.
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(deposited).build());
+        contract.register_name("alice".to_string(), accounts(1), 3 * ONE_YEAR_MILLISECOND / 2, None, accounts(1), U128(3 * DEFAULT_ONE_NEAR_YOCTO / 2));
+        assert_eq!(contract.nft_token("alice".to_string()).unwrap().owner_id, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Deposit at least one year")]
+    fn test_extend_less_than_one_year() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(context.attached_deposit(DEFAULT_ONE_NEAR_YOCTO / 2).build());
+        contract.extend(token_id, None);
+    }
+
+    #[test]
+    fn test_register_refunds_remainder() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        let deposited = DEFAULT_ONE_NEAR_YOCTO * 25 / 10 + MINT_STORAGE_COST;
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(deposited).build());
+        contract.register(token_id.clone(), accounts(1), None);
+        assert!(get_logs().contains(&format!("Charged {} yoctoNEAR to register alice", 2 * DEFAULT_ONE_NEAR_YOCTO)));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.register_name(token_id.clone(), accounts(1), 2 * ONE_YEAR_MILLISECOND, None, accounts(1), U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+        let refunds: Vec<Balance> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == accounts(1))
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::VmAction::Transfer { deposit } => Some(deposit),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(refunds.len(), 1);
+        assert!(refunds[0] > DEFAULT_ONE_NEAR_YOCTO / 2 && refunds[0] < DEFAULT_ONE_NEAR_YOCTO / 2 + MINT_STORAGE_COST);
+        assert_eq!(contract.revenue().registration, U128(2 * DEFAULT_ONE_NEAR_YOCTO));
+    }
+
+    #[test]
+    fn test_gift_renewal() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.extend(token_id.to_owned(), None);
+        assert_eq!(u64::from(contract.expired_date(token_id.to_owned()).unwrap()), 2 * ONE_YEAR_MILLISECOND);
+        assert_eq!(contract.nft_token(token_id).unwrap().owner_id, accounts(0));
+        assert!(get_logs().contains(&format!("{} renewed alice for {}", accounts(1), accounts(0))));
+    }
+
+    #[test]
+    #[should_panic(expected = "Owner does not accept renewals by others")]
+    fn test_gift_renewal_opt_out() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.register_name("alice".to_string(), accounts(0), ONE_YEAR_MILLISECOND, None, accounts(0), U128(0));
+        contract.set_accept_gift_renewals(false);
+        assert!(!contract.accepts_gift_renewals(accounts(0)));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.extend("alice".to_string(), None);
+    }
+
+    #[test]
+    fn test_referral_rewards() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_referral_fee(1_000);
+        assert_eq!(contract.referral_fee(), 1_000);
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(Contract::min_storage_balance()).build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+
+        let token_id = "alice".to_string();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(DEFAULT_ONE_NEAR_YOCTO + 2 * MINT_STORAGE_COST).build());
+        contract.register_name(token_id.clone(), accounts(0), ONE_YEAR_MILLISECOND, Some(accounts(2)), accounts(0), U128(DEFAULT_ONE_NEAR_YOCTO));
+        testing_env!(context.attached_deposit(DEFAULT_ONE_NEAR_YOCTO).build());
+        contract.extend(token_id.to_owned(), Some(accounts(2)));
+        contract.extend(token_id.to_owned(), Some(accounts(0)));
+        contract.extend(token_id, Some(accounts(3)));
+
+        let stats = contract.referral_stats(accounts(2)).unwrap();
+        assert_eq!((stats.registrations, stats.renewals), (1, 1));
+        assert_eq!(stats.earned, U128(DEFAULT_ONE_NEAR_YOCTO / 5));
+        assert_eq!(contract.referral_balance(accounts(2)), U128(DEFAULT_ONE_NEAR_YOCTO / 5));
+        assert!(contract.referral_stats(accounts(0)).is_none());
+        assert!(contract.referral_stats(accounts(3)).is_none());
+        assert_eq!(contract.revenue().referrals, U128(DEFAULT_ONE_NEAR_YOCTO / 5));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(1).build());
+        contract.claim_referral_rewards();
+        assert_eq!(contract.referral_balance(accounts(2)), U128(0));
+        assert_eq!(contract.referrers(None, None).len(), 1);
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).attached_deposit(0).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.on_referral_claim(accounts(2), U128(DEFAULT_ONE_NEAR_YOCTO / 5));
+        assert_eq!(contract.referral_balance(accounts(2)), U128(DEFAULT_ONE_NEAR_YOCTO / 5));
+    }
+
+    #[test]
+    #[should_panic(expected = "No referral rewards")]
+    fn test_claim_without_rewards() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(1).build());
+        contract.claim_referral_rewards();
+    }
+
+    #[test]
+    fn test_ft_register() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let usdc: AccountId = "usdc.near".parse().unwrap();
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_ft_price(usdc.to_owned(), U128(5_000_000));
+        assert_eq!(contract.ft_price_for_name(usdc.to_owned(), "alice".to_string(), 2), Some(U128(10_000_000)));
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(2 * MINT_STORAGE_COST).build());
+        contract.storage_deposit(None, None);
+
+        commit_name(&mut contract, "alice", accounts(1), "salt");
+        testing_env!(context.block_timestamp(MIN_COMMITMENT_AGE * 1_000_000).predecessor_account_id(usdc.to_owned()).attached_deposit(0).build());
+        let msg = r#"{"action": "register", "name": "Alice", "owner_id": "bob", "years": 2, "salt": "salt"}"#;
+        let unused = contract.ft_on_transfer(accounts(1), U128(12_000_000), msg.to_string());
+        assert!(matches!(unused, PromiseOrValue::Value(U128(2_000_000))));
+        assert_eq!(contract.nft_token("alice".to_string()).unwrap().owner_id, accounts(1));
+        assert_eq!(u64::from(contract.expired_date("alice".to_string()).unwrap()), MIN_COMMITMENT_AGE + 2 * ONE_YEAR_MILLISECOND);
+        assert_eq!(contract.ft_balance(usdc.to_owned()), U128(10_000_000));
+
+        let unused = contract.ft_on_transfer(accounts(2), U128(12_000_000), msg.to_string());
+        assert!(matches!(unused, PromiseOrValue::Value(U128(12_000_000))));
+
+        let msg = r#"{"action": "extend", "name": "ALICE", "years": 1}"#;
+        let unused = contract.ft_on_transfer(accounts(1), U128(5_000_000), msg.to_string());
+        assert!(matches!(unused, PromiseOrValue::Value(U128(0))));
+        assert_eq!(u64::from(contract.expired_date("alice".to_string()).unwrap()), MIN_COMMITMENT_AGE + 3 * ONE_YEAR_MILLISECOND);
+
+        let msg = r#"{"action": "extend", "name": "alice", "years": 0}"#;
+        let unused = contract.ft_on_transfer(accounts(1), U128(5_000_000), msg.to_string());
+        assert!(matches!(unused, PromiseOrValue::Value(U128(5_000_000))));
+        assert!(get_logs().contains(&"Extend at least one year".to_string()));
+        assert_eq!(u64::from(contract.expired_date("alice".to_string()).unwrap()), MIN_COMMITMENT_AGE + 3 * ONE_YEAR_MILLISECOND);
+    }
+
+    #[test]
+    fn test_ft_amount() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        let usdc: AccountId = "usdc.near".parse().unwrap();
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_ft_price(usdc.to_owned(), U128(5_000_000));
+        assert_eq!(contract.ft_amount(&usdc, DEFAULT_ONE_NEAR_YOCTO * 3 / 2), Some(7_500_000));
+        assert_eq!(contract.ft_amount(&usdc, 1), Some(1));
+        assert_eq!(contract.ft_amount(&usdc, u128::MAX), None);
+        contract.set_price(U128(0));
+        assert_eq!(contract.ft_amount(&usdc, DEFAULT_ONE_NEAR_YOCTO), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token not accepted")]
+    fn test_ft_register_unknown_token() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        let msg = r#"{"action": "register", "name": "alice", "owner_id": "bob", "years": 1}"#;
+        contract.ft_on_transfer(accounts(1), U128(5_000_000), msg.to_string());
+    }
+
+    fn sample_usd_pricing() -> UsdPricing {
+        UsdPricing {
+            oracle_id: "priceoracle.near".parse().unwrap(),
+            asset_id: "wrap.near".to_string(),
+            price_for_one_year: U128(5_000_000),
+            max_staleness: U64(60_000),
+        }
+    }
+
+    fn sample_price_data(timestamp: u64) -> PriceData {
+        PriceData {
+            timestamp: U64(timestamp * 1_000_000),
+            recency_duration_sec: 90,
+            prices: vec![AssetOptionalPrice {
+                asset_id: "wrap.near".to_string(),
+                // 1 NEAR = 2.5 USD
+                price: Some(Price { multiplier: U128(25000), decimals: 28 }),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_usd_to_yocto() {
+        let price = Price { multiplier: U128(25000), decimals: 28 };
+        assert_eq!(usd_to_yocto(5_000_000, &price), Some(2 * DEFAULT_ONE_NEAR_YOCTO));
+        assert_eq!(usd_to_yocto(5_000_000, &Price { multiplier: U128(0), decimals: 28 }), None);
+    }
+
+    #[test]
+    fn test_near_price_from_quote() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(100_000 * 1_000_000).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_usd_pricing(Some(sample_usd_pricing()));
+        assert_eq!(contract.near_price_from_quote(Ok(sample_price_data(90_000))), 2 * DEFAULT_ONE_NEAR_YOCTO);
+        assert_eq!(contract.near_price_from_quote(Ok(sample_price_data(10_000))), DEFAULT_ONE_NEAR_YOCTO);
+        assert_eq!(contract.near_price_from_quote(Err(PromiseError::Failed)), DEFAULT_ONE_NEAR_YOCTO);
+    }
+
+    #[test]
+    fn test_register_with_quote() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(100_000 * 1_000_000).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_usd_pricing(Some(sample_usd_pricing()));
+
+        testing_env!(context.attached_deposit(0).build());
+        let deposited = U128(3 * DEFAULT_ONE_NEAR_YOCTO);
+        contract.register_with_quote("alice".to_string(), accounts(1), accounts(1), deposited, None, Ok(sample_price_data(90_000)));
+        assert!(get_logs().contains(&format!("Charged {} yoctoNEAR to register alice", 2 * DEFAULT_ONE_NEAR_YOCTO)));
+
+        let deposited = U128(DEFAULT_ONE_NEAR_YOCTO);
+        contract.register_with_quote("bob".to_string(), accounts(1), accounts(1), deposited, None, Ok(sample_price_data(90_000)));
+        assert!(get_logs().contains(&"Deposit at least one year".to_string()));
+        assert_eq!(contract.revenue().refunds, U128(0));
+    }
+
+    #[test]
+    fn test_register_batch_gas() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        let names: Vec<String> = (0..MAX_BATCH_SIZE).map(|i| format!("{}{:02}", "n".repeat(MAX_NAME_LENGTH - 2), i)).collect();
+        for name in &names {
+            commit_name(&mut contract, name, accounts(1), "salt");
+        }
+        let items = names.iter()
+            .map(|name| BatchRegisterItem { name: name.to_owned(), owner_id: accounts(1), years: 1, salt: "salt".to_string() })
+            .collect();
+
+        testing_env!(context
+            .block_timestamp(MIN_COMMITMENT_AGE * 1_000_000)
+            .storage_usage(env::storage_usage())
+            .attached_deposit(30 * DEFAULT_ONE_NEAR_YOCTO)
+            .build());
+        let initial_storage_usage = env::storage_usage();
+        let results = contract.register_batch_with_quote(items, accounts(1), U128(30 * DEFAULT_ONE_NEAR_YOCTO), None, Err(PromiseError::Failed));
+        assert!(results.iter().all(|result| result.success));
+        assert!(env::used_gas() < BATCH_CALLBACK_GAS);
+        let storage_used = env::storage_usage() - initial_storage_usage;
+        assert!(storage_used < MAX_NAME_STORAGE_BYTES * MAX_BATCH_SIZE as u64);
+    }
+
+    #[test]
+    fn test_register_batch_with_quote_refunds() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(0).build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+        commit_name(&mut contract, "alice", accounts(1), "salt");
+        let items = vec![BatchRegisterItem { name: "alice".to_string(), owner_id: accounts(1), years: 1, salt: "salt".to_string() }];
+
+        // The deposit pays the name but not its storage.
+        testing_env!(context.block_timestamp(MIN_COMMITMENT_AGE * 1_000_000).attached_deposit(0).build());
+        let deposited = DEFAULT_ONE_NEAR_YOCTO;
+        let results = contract.register_batch_with_quote(items, accounts(1), U128(deposited), None, Err(PromiseError::Failed));
+        assert!(results.is_empty());
+        assert!(contract.nft_token("alice".to_string()).is_none());
+        assert_eq!(contract.revenue().refunds, U128(0));
+        let refunds: Vec<Balance> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == accounts(1))
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::VmAction::Transfer { deposit } => Some(deposit),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(refunds, vec![deposited]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attach at least")]
+    fn test_register_batch_with_quote_gas() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.set_usd_pricing(Some(sample_usd_pricing()));
+        let items = vec![BatchRegisterItem { name: "alice".to_string(), owner_id: accounts(1), years: 1, salt: "salt".to_string() }];
+        testing_env!(context.attached_deposit(DEFAULT_ONE_NEAR_YOCTO).prepaid_gas(Gas(100_000_000_000_000)).build());
+        let _ = contract.register_batch(items, None);
+    }
 }
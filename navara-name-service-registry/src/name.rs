@@ -0,0 +1,65 @@
+use crate::*;
+
+pub const MIN_NAME_LENGTH: usize = 1;
+pub const MAX_NAME_LENGTH: usize = 32;
+const MAX_ACCOUNT_ID_LENGTH: usize = 64;
+
+impl Contract {
+    /// Lowercases and trims `token_id`, then checks that the result can be
+    /// minted and later used as `<name>.<registry>` resolver sub-account.
+    pub fn normalize_name(&self, token_id: &str) -> Result<TokenId, String> {
+        let name = token_id.trim().to_lowercase();
+        if name.len() < MIN_NAME_LENGTH {
+            return Err(format!("Name must be at least {} characters", MIN_NAME_LENGTH));
+        }
+        if name.len() > MAX_NAME_LENGTH {
+            return Err(format!("Name must be at most {} characters", MAX_NAME_LENGTH));
+        }
+        if name.contains('.') {
+            return Err("Name must not contain '.'".to_owned());
+        }
+        if !name.bytes().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-' || c == b'_') {
+            return Err("Name may only contain a-z, 0-9, '-' and '_'".to_owned());
+        }
+        if name.starts_with(['-', '_']) || name.ends_with(['-', '_']) {
+            return Err("Name must start and end with a letter or digit".to_owned());
+        }
+        if name.as_bytes().windows(2).any(|pair| !pair[0].is_ascii_alphanumeric() && !pair[1].is_ascii_alphanumeric()) {
+            return Err("Name must not contain consecutive '-' or '_'".to_owned());
+        }
+        let account_length = name.len() + 1 + env::current_account_id().as_str().len();
+        if account_length > MAX_ACCOUNT_ID_LENGTH {
+            return Err(format!("Name is too long to be a sub-account of {}", env::current_account_id()));
+        }
+        Ok(name)
+    }
+
+    /// Same rules as `normalize_name` applied to `label`, returning the
+    /// `<label>.<parent>` token id of the subname.
+    pub fn normalize_subname(&self, parent_id: &TokenId, label: &str) -> Result<TokenId, String> {
+        let label = self.normalize_name(label)?;
+        let subname = format!("{}.{}", label, parent_id);
+        let account_length = subname.len() + 1 + env::current_account_id().as_str().len();
+        if account_length > MAX_ACCOUNT_ID_LENGTH {
+            return Err(format!("Subname is too long to be a sub-account of {}", env::current_account_id()));
+        }
+        Ok(subname)
+    }
+
+    pub fn assert_valid_name(&self, token_id: &str) -> TokenId {
+        self.normalize_name(token_id).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Normalizes a name or a `<label>.<parent>` subname passed by a caller.
+    pub fn assert_valid_token_id(&self, token_id: &str) -> TokenId {
+        match token_id.trim().split_once('.') {
+            Some((label, parent_id)) => self.normalize_name(parent_id)
+                .and_then(|parent_id| self.normalize_subname(&parent_id, label)),
+            None => self.normalize_name(token_id),
+        }.unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn resolver_account_id(&self, token_id: &TokenId) -> AccountId {
+        AccountId::try_from(format!("{}.{}", token_id, env::current_account_id())).unwrap()
+    }
+}
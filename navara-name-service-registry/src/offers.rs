@@ -0,0 +1,174 @@
+use near_sdk::json_types::U128;
+
+use crate::*;
+
+/// Offers kept per name. When a name is full, a new offer replaces an
+/// expired offer or outbids the lowest one, which is refunded.
+pub const MAX_OFFERS_PER_NAME: usize = 20;
+
+/// NEAR escrowed by `buyer_id` to buy a name until `expires_at`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Offer {
+    pub buyer_id: AccountId,
+    pub amount: U128,
+    /// Milliseconds.
+    pub expires_at: U64,
+    /// Paid by the buyer for the storage of the offer, refunded with it.
+    pub storage_deposit: U128,
+}
+
+impl Offer {
+    fn escrow(&self) -> Balance {
+        self.amount.0 + self.storage_deposit.0
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at.0 <= env::block_timestamp_ms()
+    }
+}
+
+impl Contract {
+    fn take_offer(&mut self, token_id: &TokenId, buyer_id: &AccountId) -> Option<Offer> {
+        let mut offers = self.offers.get(token_id)?;
+        let index = offers.iter().position(|offer| offer.buyer_id == *buyer_id)?;
+        let offer = offers.remove(index);
+        if offers.is_empty() {
+            self.offers.remove(token_id);
+        } else {
+            self.offers.insert(token_id, &offers);
+        }
+        self.total_offers -= offer.escrow();
+        Some(offer)
+    }
+
+    fn refund_offer(&self, offer: &Offer) {
+        Promise::new(offer.buyer_id.to_owned()).transfer(offer.escrow());
+    }
+
+    /// Refunds an expired offer on `token_id`, or the lowest offer when
+    /// `amount` is higher, to make room for a new offer.
+    fn evict_offer(&mut self, token_id: &TokenId, amount: Balance) {
+        let offers = self.offers.get(token_id).unwrap_or_default();
+        let evicted = offers
+            .iter()
+            .find(|offer| offer.is_expired())
+            .or_else(|| offers.iter().min_by_key(|offer| offer.amount.0))
+            .unwrap();
+        assert!(
+            evicted.is_expired() || evicted.amount.0 < amount,
+            "Too many offers, offer more than {} yoctoNEAR", evicted.amount.0
+        );
+        let offer = self.take_offer(token_id, &evicted.buyer_id).unwrap();
+        self.refund_offer(&offer);
+    }
+
+    /// Refunds every offer on `token_id`, when the name is burned.
+    pub fn internal_refund_offers(&mut self, token_id: &TokenId) {
+        for offer in self.offers.remove(token_id).unwrap_or_default() {
+            self.total_offers -= offer.escrow();
+            self.refund_offer(&offer);
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Escrows `amount` to buy `token_id` until `expires_at`. The rest of the
+    /// deposit pays for the storage of the offer and the excess is refunded.
+    /// A new offer replaces the previous offer of the caller. Offers below
+    /// their storage cost are rejected, so that slots cannot be filled for
+    /// free.
+    #[payable]
+    pub fn make_offer(&mut self, token_id: TokenId, amount: U128, expires_at: U64) -> Offer {
+        Self::require_unpaused();
+        let token_id = self.assert_valid_token_id(&token_id);
+        let buyer_id = env::predecessor_account_id();
+        let token = self.nft_token(token_id.to_owned()).expect("Name not found");
+        assert_ne!(token.owner_id, buyer_id, "Owner cannot make an offer");
+        assert_ne!(self.name_state_of(&token_id), NameState::Available, "Name expired");
+        assert!(amount.0 > 0, "Offer must be positive");
+        assert!(expires_at.0 > env::block_timestamp_ms(), "Invalid expiry");
+        let deposited = env::attached_deposit();
+        assert!(deposited >= amount.0, "Deposit at least the offer");
+
+        let previous = self.take_offer(&token_id, &buyer_id);
+        if self.offers.get(&token_id).map(|offers| offers.len()).unwrap_or(0) >= MAX_OFFERS_PER_NAME {
+            self.evict_offer(&token_id, amount.0);
+        }
+        let mut offers = self.offers.get(&token_id).unwrap_or_default();
+        let initial_storage_usage = env::storage_usage();
+        let mut offer = Offer {
+            buyer_id: buyer_id.to_owned(),
+            amount,
+            expires_at,
+            storage_deposit: U128(0),
+        };
+        offers.push(offer.clone());
+        self.offers.insert(&token_id, &offers);
+        let storage_deposit = Balance::from(env::storage_usage().saturating_sub(initial_storage_usage)) * env::storage_byte_cost();
+        assert!(amount.0 >= storage_deposit, "Offer at least its storage cost of {} yoctoNEAR", storage_deposit);
+        let available = deposited + previous.as_ref().map(Offer::escrow).unwrap_or(0);
+        assert!(available >= amount.0 + storage_deposit, "Deposit at least {} yoctoNEAR", amount.0 + storage_deposit);
+
+        offer.storage_deposit = U128(storage_deposit);
+        *offers.last_mut().unwrap() = offer.clone();
+        self.offers.insert(&token_id, &offers);
+        self.total_offers += offer.escrow();
+        let refund = available - offer.escrow();
+        if refund > 0 {
+            Promise::new(buyer_id).transfer(refund);
+        }
+        offer
+    }
+
+    pub fn withdraw_offer(&mut self, token_id: TokenId) -> Promise {
+        let token_id = self.assert_valid_token_id(&token_id);
+        let offer = self.take_offer(&token_id, &env::predecessor_account_id()).expect("Offer not found");
+        Promise::new(offer.buyer_id.to_owned()).transfer(offer.escrow())
+    }
+
+    /// Sells `token_id` to `buyer_id` for its offer. The name moves through
+    /// `nft_transfer`, so it needs one yoctoNEAR and an unexpired name, and
+    /// the price is paid out with the NEP-199 royalties.
+    #[payable]
+    pub fn accept_offer(&mut self, token_id: TokenId, buyer_id: AccountId) -> Payout {
+        let token_id = self.assert_valid_token_id(&token_id);
+        let token = self.token_owner_only(&token_id);
+        let offer = self.take_offer(&token_id, &buyer_id).expect("Offer not found");
+        assert!(!offer.is_expired(), "Offer expired");
+        let payout = self.internal_payout(&token_id, &token.owner_id, offer.amount.0, None);
+        self.nft_transfer(buyer_id.to_owned(), token_id, None, Some("offer accepted".to_owned()));
+        for (account_id, amount) in payout.payout.iter() {
+            if *account_id == env::current_account_id() {
+                self.record_revenue(RevenueKind::Royalty, amount.0);
+            } else {
+                Promise::new(account_id.to_owned()).transfer(amount.0);
+            }
+        }
+        if offer.storage_deposit.0 > 0 {
+            Promise::new(buyer_id).transfer(offer.storage_deposit.0);
+        }
+        payout
+    }
+
+    /// Refunds the expired offers on `token_id`. Anyone can call it.
+    pub fn refund_expired_offers(&mut self, token_id: TokenId) -> u32 {
+        let expired: Vec<AccountId> = self.offers
+            .get(&token_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(Offer::is_expired)
+            .map(|offer| offer.buyer_id)
+            .collect();
+        for buyer_id in expired.iter() {
+            let offer = self.take_offer(&token_id, buyer_id).unwrap();
+            self.refund_offer(&offer);
+        }
+        expired.len() as u32
+    }
+
+    pub fn offers_for_name(&self, token_id: TokenId) -> Vec<Offer> {
+        self.offers.get(&token_id).unwrap_or_default()
+    }
+}
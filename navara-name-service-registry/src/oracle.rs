@@ -0,0 +1,155 @@
+use near_sdk::json_types::U128;
+use near_sdk::{ext_contract, assert_one_yocto, PromiseError};
+
+use crate::*;
+
+const ORACLE_GAS: Gas = Gas(10_000_000_000_000);
+pub const USD_DECIMALS: u32 = 6;
+
+/// Prices the base yearly price in USD and converts it to yoctoNEAR with a
+/// price oracle at registration and renewal time. Length tiers stay in
+/// yoctoNEAR.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UsdPricing {
+    pub oracle_id: AccountId,
+    pub asset_id: String,
+    /// Base price of one year, in millionths of a USD.
+    pub price_for_one_year: U128,
+    /// Oldest oracle price accepted, in milliseconds.
+    pub max_staleness: U64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Price {
+    pub multiplier: U128,
+    pub decimals: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetOptionalPrice {
+    pub asset_id: String,
+    pub price: Option<Price>,
+}
+
+/// Response of `get_price_data` on the NEAR price oracle.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceData {
+    /// Nanoseconds.
+    pub timestamp: U64,
+    pub recency_duration_sec: u32,
+    pub prices: Vec<AssetOptionalPrice>,
+}
+
+#[ext_contract(ext_price_oracle)]
+pub trait PriceOracle {
+    fn get_price_data(&self, asset_ids: Option<Vec<String>>) -> PriceData;
+}
+
+impl Contract {
+    pub fn quote_near_price(&self) -> Promise {
+        let usd_pricing = self.usd_pricing.as_ref().unwrap();
+        ext_price_oracle::ext(usd_pricing.oracle_id.to_owned())
+            .with_static_gas(ORACLE_GAS)
+            .get_price_data(Some(vec![usd_pricing.asset_id.to_owned()]))
+    }
+
+    /// Base yearly price in yoctoNEAR for a quote returned by the oracle.
+    /// Falls back to `price_for_one_year` when the quote is missing or stale.
+    pub fn near_price_from_quote(&self, price_data: Result<PriceData, PromiseError>) -> Balance {
+        let usd_pricing = match &self.usd_pricing {
+            Some(usd_pricing) => usd_pricing,
+            None => return self.price_for_one_year,
+        };
+        let price_data = match price_data {
+            Ok(price_data) => price_data,
+            Err(_) => {
+                env::log_str("Price oracle failed, using NEAR price");
+                return self.price_for_one_year;
+            }
+        };
+        let quoted_at = price_data.timestamp.0 / 1_000_000;
+        if quoted_at + usd_pricing.max_staleness.0 < env::block_timestamp_ms() {
+            env::log_str("Price oracle is stale, using NEAR price");
+            return self.price_for_one_year;
+        }
+        price_data.prices
+            .into_iter()
+            .find(|asset| asset.asset_id == usd_pricing.asset_id)
+            .and_then(|asset| asset.price)
+            .and_then(|price| usd_to_yocto(usd_pricing.price_for_one_year.0, &price))
+            .unwrap_or_else(|| {
+                env::log_str("Price oracle has no price, using NEAR price");
+                self.price_for_one_year
+            })
+    }
+}
+
+/// Converts millionths of a USD to yoctoNEAR, given the oracle price of one
+/// yoctoNEAR: `multiplier / 10^decimals` USD.
+pub fn usd_to_yocto(usd: u128, price: &Price) -> Option<Balance> {
+    if price.multiplier.0 == 0 {
+        return None;
+    }
+    let numerator = usd.checked_mul(10u128.checked_pow(price.decimals as u32)?)?;
+    let denominator = price.multiplier.0.checked_mul(10u128.pow(USD_DECIMALS))?;
+    Some(numerator / denominator).filter(|yocto| *yocto > 0)
+}
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn set_usd_pricing(&mut self, usd_pricing: Option<UsdPricing>) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        self.usd_pricing = usd_pricing;
+    }
+
+    pub fn usd_pricing(&self) -> Option<UsdPricing> {
+        self.usd_pricing.to_owned()
+    }
+
+    /// Registers at the quoted price. On failure nothing was charged, so the
+    /// deposit is returned without a refund in the treasury ledger, as in
+    /// `extend_with_quote`.
+    #[private]
+    pub fn register_with_quote(
+        &mut self,
+        token_id: TokenId,
+        token_owner_id: AccountId,
+        payer: AccountId,
+        deposited: U128,
+        referrer: Option<AccountId>,
+        #[callback_result] price_data: Result<PriceData, PromiseError>
+    ) -> Promise {
+        let base_price = self.near_price_from_quote(price_data);
+        self.register_with_price(token_id, token_owner_id, payer.to_owned(), deposited.0, base_price, referrer)
+            .unwrap_or_else(|err| {
+                env::log_str(&err);
+                Promise::new(payer).transfer(deposited.0)
+            })
+    }
+
+    #[private]
+    pub fn extend_with_quote(
+        &mut self,
+        token_id: TokenId,
+        payer: AccountId,
+        deposited: U128,
+        referrer: Option<AccountId>,
+        #[callback_result] price_data: Result<PriceData, PromiseError>
+    ) -> U128 {
+        let base_price = self.near_price_from_quote(price_data);
+        match self.extend_with_price(token_id, payer.to_owned(), deposited.0, base_price, referrer) {
+            Ok(charged) => U128::from(charged),
+            Err(err) => {
+                env::log_str(&err);
+                Promise::new(payer).transfer(deposited.0);
+                U128(0)
+            }
+        }
+    }
+}
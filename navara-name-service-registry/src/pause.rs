@@ -0,0 +1,24 @@
+use near_sdk::assert_one_yocto;
+use near_sdk_contract_tools::pause::Pause;
+
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Halts registrations, renewals, subnames, primary names, resolver
+    /// setup and NFT transfers. Views keep working. Resolvers are separate
+    /// contracts, halt them with `pause_resolvers`.
+    #[payable]
+    pub fn pause(&mut self) {
+        assert_one_yocto();
+        Self::require_role(&Role::Moderator);
+        Pause::pause(self);
+    }
+
+    #[payable]
+    pub fn unpause(&mut self) {
+        assert_one_yocto();
+        Self::require_role(&Role::Moderator);
+        Pause::unpause(self);
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use near_sdk::{json_types::U128, assert_one_yocto};
+
+use crate::*;
+
+/// NEP-199 payout of a sale, in yoctoNEAR per account.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+/// Royalties of secondary sales, in basis points of the sale price.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Royalties {
+    /// Paid to the registry account.
+    pub treasury: u16,
+    /// Paid to the account that registered the name, unless it sells it.
+    pub registrant: u16,
+}
+
+fn royalty(balance: Balance, basis_points: u16) -> Balance {
+    balance * basis_points as Balance / MAX_BASIS_POINTS as Balance
+}
+
+fn credit(payout: &mut HashMap<AccountId, U128>, account_id: AccountId, amount: Balance) {
+    if amount > 0 {
+        payout.entry(account_id).or_insert(U128(0)).0 += amount;
+    }
+}
+
+impl Contract {
+    pub fn internal_payout(&self, token_id: &TokenId, owner_id: &AccountId, balance: Balance, max_len_payout: Option<u32>) -> Payout {
+        let mut payout = HashMap::new();
+        let treasury = royalty(balance, self.royalties.treasury);
+        credit(&mut payout, env::current_account_id(), treasury);
+        let registrant = match self.registrants.get(token_id).filter(|registrant_id| registrant_id != owner_id) {
+            Some(registrant_id) => {
+                let amount = royalty(balance, self.royalties.registrant);
+                credit(&mut payout, registrant_id, amount);
+                amount
+            }
+            None => 0,
+        };
+        credit(&mut payout, owner_id.to_owned(), balance - treasury - registrant);
+        if let Some(max_len_payout) = max_len_payout {
+            assert!(payout.len() <= max_len_payout as usize, "Too many payout receivers");
+        }
+        Payout { payout }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: Option<u32>) -> Payout {
+        let owner_id = self.tokens.owner_by_id.get(&token_id).expect("Token not found");
+        self.internal_payout(&token_id, &owner_id, balance.0, max_len_payout)
+    }
+
+    /// Transfers `token_id` with the same checks as `nft_transfer` and
+    /// returns the payout of the sale computed for the previous owner.
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        let owner_id = self.tokens.owner_by_id.get(&token_id).expect("Token not found");
+        let payout = self.internal_payout(&token_id, &owner_id, balance.0, max_len_payout);
+        self.nft_transfer(receiver_id, token_id, approval_id, memo);
+        payout
+    }
+
+    #[payable]
+    pub fn set_royalties(&mut self, royalties: Royalties) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        assert!(royalties.treasury + royalties.registrant <= MAX_BASIS_POINTS, "Invalid royalties");
+        self.royalties = royalties;
+    }
+
+    pub fn royalties(&self) -> Royalties {
+        self.royalties
+    }
+
+    pub fn registrant_of(&self, token_id: TokenId) -> Option<AccountId> {
+        self.registrants.get(&token_id)
+    }
+}
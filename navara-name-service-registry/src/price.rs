@@ -2,12 +2,66 @@ use near_sdk::{json_types::U128, assert_one_yocto};
 
 use crate::*;
 
+impl Contract {
+    /// Yearly price of `token_id`: the tier with the greatest length not
+    /// above the name length, or `price_for_one_year` when no tier applies.
+    pub fn yearly_price(&self, token_id: &TokenId) -> Balance {
+        self.yearly_price_for(token_id, self.price_for_one_year)
+    }
+
+    /// Same as `yearly_price` with `base_price` used for names without a tier.
+    pub fn yearly_price_for(&self, token_id: &TokenId, base_price: Balance) -> Balance {
+        let length = token_id.chars().count() as u32;
+        self.price_by_length
+            .floor_key(&length)
+            .and_then(|tier| self.price_by_length.get(&tier))
+            .unwrap_or(base_price)
+    }
+
+    /// Extension bought by `amount` for `token_id` and the part of `amount`
+    /// actually charged: whole years only, or everything when pro-rata
+    /// charging is on.
+    pub fn purchased_duration(&self, token_id: &TokenId, amount: Balance, base_price: Balance) -> (u64, Balance) {
+        let price = self.yearly_price_for(token_id, base_price);
+        if self.charge_pro_rata {
+            // Whole years first, so that only the remainder, below `price`,
+            // is multiplied by the length of a year.
+            let year = ONE_YEAR_MILLISECOND as Balance;
+            let rest = amount % price;
+            let rest_duration = rest
+                .checked_mul(year)
+                .map(|rest| rest / price)
+                .unwrap_or_else(|| rest / (price / year));
+            let duration = amount / price * year + rest_duration;
+            (duration as u64, amount)
+        } else {
+            let years = amount / price;
+            (years as u64 * ONE_YEAR_MILLISECOND, years * price)
+        }
+    }
+
+    /// Premium charged on top of the yearly price for a name that was
+    /// released recently. Decays linearly to zero over `premium_duration`.
+    pub fn premium_of(&self, token_id: &TokenId) -> Balance {
+        let released_at = match self.released_at(token_id) {
+            Some(released_at) => released_at,
+            None => return 0,
+        };
+        let now = env::block_timestamp_ms();
+        if now < released_at || now >= released_at + self.premium_duration {
+            return 0;
+        }
+        let remaining = released_at + self.premium_duration - now;
+        self.premium_start * remaining as Balance / self.premium_duration as Balance
+    }
+}
+
 #[near_bindgen]
 impl Contract {
     #[payable]
     pub fn set_price(&mut self, price: U128) {
         assert_one_yocto();
-        Self::require_owner();
+        Self::require_role(&Role::PricingManager);
         let new_price = u128::from(price);
         self.price_for_one_year = new_price;
     }
@@ -16,15 +70,67 @@ impl Contract {
         U128::from(self.price_for_one_year)
     }
 
+    /// Deprecated: registration no longer charges a flat fee for storage,
+    /// the measured storage is charged through `storage_deposit` balances or
+    /// the attached deposit. Kept so that old callers get a clear error.
     #[payable]
-    pub fn set_fee_register(&mut self, price: U128) {
+    pub fn set_fee_register(&mut self, #[allow(unused_variables)] price: U128) {
         assert_one_yocto();
-        Self::require_owner();
-        let new_fee = u128::from(price);
-        self.fee_register = new_fee;
+        Self::require_role(&Role::PricingManager);
+        panic!("Deprecated: storage is charged at cost, see storage_balance_bounds");
     }
 
+    /// Deprecated: always zero, see `set_fee_register`.
     pub fn fee_register(&self) -> U128 {
-        U128::from(self.fee_register)
+        U128(0)
+    }
+
+    #[payable]
+    pub fn set_price_for_length(&mut self, length: u32, price: U128) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        assert!(length > 0, "Invalid length");
+        self.price_by_length.insert(&length, &u128::from(price));
+    }
+
+    #[payable]
+    pub fn remove_price_for_length(&mut self, length: u32) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        self.price_by_length.remove(&length);
+    }
+
+    pub fn price_tiers(&self) -> Vec<(u32, U128)> {
+        self.price_by_length
+            .iter()
+            .map(|(length, price)| (length, U128::from(price)))
+            .collect()
+    }
+
+    pub fn price_for_name(&self, token_id: TokenId) -> U128 {
+        U128::from(self.yearly_price(&token_id))
+    }
+
+    #[payable]
+    pub fn set_premium(&mut self, start_premium: U128, decay_days: u64) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        self.premium_start = start_premium.into();
+        self.premium_duration = decay_days * ONE_DAY_MILLISECOND;
+    }
+
+    pub fn premium(&self, token_id: TokenId) -> U128 {
+        U128::from(self.premium_of(&token_id))
+    }
+
+    #[payable]
+    pub fn set_charge_pro_rata(&mut self, enabled: bool) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        self.charge_pro_rata = enabled;
+    }
+
+    pub fn charge_pro_rata(&self) -> bool {
+        self.charge_pro_rata
     }
-}
\ No newline at end of file
+}
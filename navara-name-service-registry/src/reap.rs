@@ -0,0 +1,85 @@
+use near_sdk::{json_types::U128, assert_one_yocto};
+
+use crate::*;
+
+impl Contract {
+    /// A name can be reaped once it is available again and its premium
+    /// auction is over.
+    pub fn is_reapable(&self, token_id: &TokenId) -> bool {
+        if self.is_subname(token_id) || self.name_state_of(token_id) != NameState::Available {
+            return false;
+        }
+        self.released_at(token_id)
+            .map(|released_at| released_at + self.premium_duration <= env::block_timestamp_ms())
+            .unwrap_or(false)
+    }
+
+    /// Burns `token_id` and its subnames. The storage freed goes back to
+    /// whoever paid for it, minus the reward returned for the reaper.
+    fn internal_reap(&mut self, token_id: &TokenId) -> Balance {
+        self.internal_reap_subnames(token_id, self.reap_reward) + self.internal_reap_one(token_id, self.reap_reward)
+    }
+
+    /// Burns the subnames of `parent_id`, keeping `reward` basis points of
+    /// their storage and crediting the rest to their storage payers.
+    pub fn internal_reap_subnames(&mut self, parent_id: &TokenId, reward: u16) -> Balance {
+        self.subnames
+            .get(parent_id)
+            .unwrap_or_default()
+            .iter()
+            .map(|subname| self.internal_reap_one(subname, reward))
+            .sum()
+    }
+
+    fn internal_reap_one(&mut self, token_id: &TokenId, reward: u16) -> Balance {
+        self.internal_settle_before_burn(token_id);
+        let payer = self.storage_payers.get(token_id);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_burn(token_id);
+        let storage_released = initial_storage_usage.saturating_sub(env::storage_usage());
+        let released = Balance::from(storage_released) * env::storage_byte_cost();
+        let reward = released * reward as Balance / MAX_BASIS_POINTS as Balance;
+        if let Some(payer) = payer {
+            self.credit_storage(&payer, released - reward);
+        }
+        reward
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Burns long-expired names to free their storage. Anyone can call it
+    /// and receives `reap_reward` basis points of the storage freed, the
+    /// rest is credited to the storage payers.
+    pub fn reap_expired(&mut self, names: Vec<TokenId>) -> U128 {
+        Self::require_unpaused();
+        assert!(!names.is_empty() && names.len() <= MAX_BATCH_SIZE, "Batch must have 1 to {} names", MAX_BATCH_SIZE);
+        let mut reward = 0;
+        for name in names {
+            let token_id = match self.normalize_name(&name) {
+                Ok(token_id) if self.nft_token(token_id.to_owned()).is_some() && self.is_reapable(&token_id) => token_id,
+                _ => {
+                    env::log_str(&format!("{} cannot be reaped", name));
+                    continue;
+                }
+            };
+            reward += self.internal_reap(&token_id);
+        }
+        if reward > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(reward);
+        }
+        U128::from(reward)
+    }
+
+    #[payable]
+    pub fn set_reap_reward(&mut self, basis_points: u16) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        assert!(basis_points <= MAX_BASIS_POINTS, "Invalid reap reward");
+        self.reap_reward = basis_points;
+    }
+
+    pub fn reap_reward(&self) -> u16 {
+        self.reap_reward
+    }
+}
@@ -0,0 +1,110 @@
+use near_sdk::{json_types::U128, assert_one_yocto};
+
+use crate::*;
+
+pub const MAX_BASIS_POINTS: u16 = 10_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReferralStats {
+    pub registrations: u64,
+    pub renewals: u64,
+    pub earned: U128,
+    pub claimed: U128,
+}
+
+impl Default for ReferralStats {
+    fn default() -> Self {
+        Self {
+            registrations: 0,
+            renewals: 0,
+            earned: U128(0),
+            claimed: U128(0),
+        }
+    }
+}
+
+impl Contract {
+    /// Drops self-referrals so payers cannot discount their own names, and
+    /// referrers without a storage registration, which pays for their entry.
+    pub fn valid_referrer(&self, referrer: Option<AccountId>, payer: &AccountId) -> Option<AccountId> {
+        referrer.filter(|referrer| referrer != payer && self.storage_balances.contains_key(referrer))
+    }
+
+    /// Credits `referrer` with `referral_fee` basis points of `charged`.
+    pub fn accrue_referral(&mut self, referrer: Option<AccountId>, charged: Balance, kind: RevenueKind) {
+        let referrer = match referrer {
+            Some(referrer) => referrer,
+            None => return,
+        };
+        let reward = charged * self.referral_fee as Balance / MAX_BASIS_POINTS as Balance;
+        let mut stats = self.referrals.get(&referrer).unwrap_or_default();
+        match kind {
+            RevenueKind::Renewal => stats.renewals += 1,
+            _ => stats.registrations += 1,
+        }
+        stats.earned.0 += reward;
+        self.referrals.insert(&referrer, &stats);
+        self.referral_liability += reward;
+        self.record_revenue(RevenueKind::Referral, reward);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn set_referral_fee(&mut self, basis_points: u16) {
+        assert_one_yocto();
+        Self::require_role(&Role::PricingManager);
+        assert!(basis_points <= MAX_BASIS_POINTS, "Invalid referral fee");
+        self.referral_fee = basis_points;
+    }
+
+    pub fn referral_fee(&self) -> u16 {
+        self.referral_fee
+    }
+
+    /// Sends the unclaimed rewards of the caller to the caller.
+    #[payable]
+    pub fn claim_referral_rewards(&mut self) -> Promise {
+        assert_one_yocto();
+        let referrer = env::predecessor_account_id();
+        let mut stats = self.referrals.get(&referrer).expect("No referral rewards");
+        let amount = stats.earned.0 - stats.claimed.0;
+        assert!(amount > 0, "No referral rewards");
+        stats.claimed = stats.earned;
+        self.referrals.insert(&referrer, &stats);
+        self.referral_liability -= amount;
+        Promise::new(referrer.to_owned())
+            .transfer(amount)
+            .then(Self::ext(env::current_account_id()).on_referral_claim(referrer, U128::from(amount)))
+    }
+
+    /// Restores the claimed rewards when the transfer failed.
+    #[private]
+    pub fn on_referral_claim(&mut self, referrer: AccountId, amount: U128) {
+        if let PromiseResult::Failed = env::promise_result(0) {
+            let mut stats = self.referrals.get(&referrer).unwrap_or_default();
+            stats.claimed.0 -= amount.0;
+            self.referrals.insert(&referrer, &stats);
+            self.referral_liability += amount.0;
+        }
+    }
+
+    pub fn referral_stats(&self, account_id: AccountId) -> Option<ReferralStats> {
+        self.referrals.get(&account_id)
+    }
+
+    pub fn referral_balance(&self, account_id: AccountId) -> U128 {
+        let stats = self.referrals.get(&account_id).unwrap_or_default();
+        U128::from(stats.earned.0 - stats.claimed.0)
+    }
+
+    pub fn referrers(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<(AccountId, ReferralStats)> {
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+        self.referrals.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+}
@@ -1,11 +1,14 @@
+use near_sdk::json_types::U128;
+
 use crate::*;
 
 impl Contract {
-    fn metadata_by_token(&self, token_id: &TokenId) -> TokenMetadata {
+    pub fn metadata_by_token(&self, token_id: &TokenId) -> TokenMetadata {
         TokenMetadata {
             title: Some(format!("{}.nns", token_id.to_owned())),
             description: Some("Navara name service powered by NEAR protocol".into()),
-            media: Some(DATA_IMAGE_SVG_NEAR_ICON.to_owned()),
+            // Generated from the name and its expiry by the views.
+            media: None,
             media_hash: None,
             copies: Some(1u64),
             issued_at: None,
@@ -24,67 +27,201 @@ impl Contract {
         }
         false
     }
-}
 
-#[near_bindgen]
-impl Contract {
-    #[payable]
-    pub fn register(
-        &mut self, 
-        token_id: TokenId, 
-        token_owner_id: AccountId
-    ) -> Promise {
-        if let Some(name_expired_date) = self.name_expired_date.get(&token_id) {
-            assert!(name_expired_date < env::block_timestamp_ms(), "Unexpired");
+    /// Removes the primary name of `account_id` and credits its storage
+    /// back, as the account paid for it in `set_default`.
+    pub fn internal_remove_default(&mut self, account_id: &AccountId) {
+        let initial_storage_usage = env::storage_usage();
+        if self.default_name.remove(account_id).is_some() {
+            let storage_released = initial_storage_usage.saturating_sub(env::storage_usage());
+            self.credit_storage(account_id, Balance::from(storage_released) * env::storage_byte_cost());
+            NnsEvent::PrimaryNameChange(vec![PrimaryNameChangeData {
+                account_id: account_id.to_owned(),
+                token_id: None,
+            }]).emit();
         }
-        let deposited = env::attached_deposit() - self.fee_register;
-        assert!(deposited >= self.price_for_one_year, "Deposit at least one NEAR");
-        let years_extended: u64 = (deposited / self.price_for_one_year).try_into().unwrap();
-        let token_metadata = self.metadata_by_token(&token_id);
-        Self::ext(env::current_account_id()).with_attached_deposit(deposited).register_name(token_id.to_owned(), token_owner_id.to_owned(), token_metadata, years_extended).then(
-            Self::ext(env::current_account_id()).failure_resolve(token_owner_id, deposited)
+    }
+
+    /// Drops what the previous owner of a released name left on it: the
+    /// approvals of its marketplaces, the offers made to it and the version
+    /// of its resolver.
+    fn internal_clear_ownership(&mut self, token_id: &TokenId) {
+        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+            approvals_by_id.remove(token_id);
+        }
+        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
+            next_approval_id_by_id.remove(token_id);
+        }
+        self.internal_refund_offers(token_id);
+        self.internal_remove_resolver_version(token_id);
+    }
+
+    pub fn internal_register(&mut self, token_id: TokenId, token_owner_id: AccountId, referrer: Option<AccountId>) -> Promise {
+        Self::require_unpaused();
+        assert_eq!(self.name_state_of(&token_id), NameState::Available, "Name not available");
+        assert!(!self.is_reserved(&token_id), "Name is reserved");
+        let payer = env::predecessor_account_id();
+        let deposited = env::attached_deposit();
+        let referrer = self.valid_referrer(referrer, &payer);
+        if self.usd_pricing.is_some() {
+            return self.quote_near_price().then(
+                Self::ext(env::current_account_id()).register_with_quote(token_id, token_owner_id, payer, U128::from(deposited), referrer)
+            )
+        }
+        self.register_with_price(token_id, token_owner_id, payer, deposited, self.price_for_one_year, referrer)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Charges `payer` for `token_id` with `base_price` as the yearly price
+    /// of names without a length tier. The rest of the deposit pays for the
+    /// storage of the name and what is not needed is refunded.
+    pub fn register_with_price(
+        &mut self,
+        token_id: TokenId,
+        token_owner_id: AccountId,
+        payer: AccountId,
+        deposited: Balance,
+        base_price: Balance,
+        referrer: Option<AccountId>
+    ) -> Result<Promise, String> {
+        if self.name_state_of(&token_id) != NameState::Available {
+            return Err("Name not available".to_owned());
+        }
+        let price = self.yearly_price_for(&token_id, base_price);
+        let premium = self.premium_of(&token_id);
+        if deposited < premium + price {
+            return Err("Deposit at least one year".to_owned());
+        }
+        // Pro-rata charging spends the whole amount, so the storage of the
+        // name is set aside first. What it does not use is refunded.
+        let storage_reserve = if self.charge_pro_rata {
+            Balance::from(MAX_NAME_STORAGE_BYTES) * env::storage_byte_cost()
+        } else {
+            0
+        };
+        if deposited < premium + price + storage_reserve {
+            return Err(format!("Deposit at least {} yoctoNEAR", premium + price + storage_reserve));
+        }
+        let (duration, charged) = self.purchased_duration(&token_id, deposited - premium - storage_reserve, base_price);
+        let charged = charged + premium;
+        env::log_str(&format!("Charged {} yoctoNEAR to register {}", charged, token_id));
+        Ok(Self::ext(env::current_account_id())
+            .with_attached_deposit(deposited)
+            .register_name(token_id.to_owned(), token_owner_id.to_owned(), duration, referrer, payer.to_owned(), U128::from(charged))
+            .then(Self::ext(env::current_account_id()).failure_resolve(payer, deposited))
         )
     }
 
-    #[private]
-    #[payable]
-    pub fn register_name(
+    /// Mints `token_id`, or hands a released name over to its new owner.
+    /// Storage is paid by the registry, `charged` is only reported in the event.
+    pub fn internal_register_name(
         &mut self,
         token_id: TokenId,
         token_owner_id: AccountId,
         token_metadata: TokenMetadata,
-        years_extended: u64
+        duration: u64,
+        charged: Balance,
+        ft_token_id: Option<AccountId>
     ) -> Token {
-        let signer = env::signer_account_id();
-        let new_expired_date = env::block_timestamp_ms() + (years_extended * ONE_YEAR_MILLISECOND);
+        let new_expired_date = env::block_timestamp_ms() + duration;
         self.update_expired_date(&token_id, &new_expired_date);
-        if let Some(token) = self.nft_token(token_id.to_owned()) {
+        self.registrants.insert(&token_id, &token_owner_id);
+        let token = if let Some(token) = self.nft_token(token_id.to_owned()) {
             if self.is_default_name(&token) {
-                self.remove_default()
-            }
-            if let Some(default_name) = self.default_name(token.owner_id.to_owned()) {
-                if default_name.eq(&token_id) {
-                    self.remove_default()
-                }
+                self.internal_remove_default(&token.owner_id)
             }
-            self.tokens.internal_transfer_unguarded(&token_id, &token.owner_id, &signer);
+            self.internal_clear_ownership(&token_id);
+            // Subnames were given out by the previous owner.
+            self.internal_reap_subnames(&token_id, 0);
+            self.tokens.internal_transfer_unguarded(&token_id, &token.owner_id, &token_owner_id);
             self.nft_token(token_id).unwrap()
         } else {
-            self.tokens.internal_mint(token_id, token_owner_id, Some(token_metadata))
+            self.tokens.internal_mint_with_refund(token_id, token_owner_id, Some(token_metadata), None)
+        };
+        NnsEvent::NameRegister(vec![NameRegisterData {
+            token_id: token.token_id.to_owned(),
+            owner_id: token.owner_id.to_owned(),
+            expired_date: U64::from(new_expired_date),
+            charged: U128::from(charged),
+            ft_token_id,
+        }]).emit();
+        token
+    }
+
+    /// Registers `token_id` paid by the attached deposit. Not exposed: public
+    /// registration goes through `reveal_register`, and `admin_register` is
+    /// the only bypass.
+    pub fn register(
+        &mut self, 
+        token_id: TokenId, 
+        token_owner_id: AccountId,
+        referrer: Option<AccountId>
+    ) -> Promise {
+        let token_id = self.assert_valid_name(&token_id);
+        self.internal_register(token_id, token_owner_id, referrer)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+
+    #[private]
+    #[payable]
+    pub fn register_name(
+        &mut self,
+        token_id: TokenId,
+        token_owner_id: AccountId,
+        duration: u64,
+        referrer: Option<AccountId>,
+        payer: AccountId,
+        charged: U128
+    ) -> Token {
+        // Another registration of the same name may have landed since the
+        // call was scheduled, panicking lets `failure_resolve` refund.
+        assert_eq!(self.name_state_of(&token_id), NameState::Available, "Name not available");
+        assert!(!self.is_reserved(&token_id), "Name is reserved");
+        let charged = u128::from(charged);
+        let storage_deposit = env::attached_deposit() - charged;
+        self.record_revenue(RevenueKind::Registration, charged);
+        self.accrue_referral(referrer, charged, RevenueKind::Registration);
+        let token_metadata = self.metadata_by_token(&token_id);
+        let initial_storage_usage = env::storage_usage();
+        let token = self.internal_register_name(token_id, token_owner_id, token_metadata, duration, charged, None);
+        self.internal_set_storage_payer(&token.token_id, &payer);
+        let storage_used = env::storage_usage().saturating_sub(initial_storage_usage);
+        let refund = self.charge_storage(&payer, storage_used, storage_deposit);
+        if refund > 0 {
+            Promise::new(payer).transfer(refund);
         }
+        token
     }
     
+    /// Storage of the primary name is paid by the attached deposit, then by
+    /// the storage balance of the caller.
+    #[payable]
     pub fn set_default(&mut self, token_id: TokenId) {
+        Self::require_unpaused();
+        let token_id = self.assert_valid_token_id(&token_id);
         let account_id = env::predecessor_account_id();
         if let Some(token) = self.nft_token(token_id.to_owned()) {
             assert_eq!(token.owner_id, account_id, "Only name owner");
         }
+        let initial_storage_usage = env::storage_usage();
         self.default_name.insert(&account_id, &token_id);
+        let storage_used = env::storage_usage().saturating_sub(initial_storage_usage);
+        let refund = self.charge_storage(&account_id, storage_used, env::attached_deposit());
+        if refund > 0 {
+            Promise::new(account_id.to_owned()).transfer(refund);
+        }
+        NnsEvent::PrimaryNameChange(vec![PrimaryNameChangeData {
+            account_id,
+            token_id: Some(token_id),
+        }]).emit();
     }
 
     pub fn remove_default(&mut self) {
         let account_id = env::predecessor_account_id();
-        self.default_name.remove(&account_id);
+        self.internal_remove_default(&account_id);
     }
 
     pub fn default_name(&self, account_id: AccountId) -> Option<TokenId> {
@@ -0,0 +1,59 @@
+use near_sdk::{json_types::U128, assert_one_yocto};
+
+use crate::*;
+
+impl Contract {
+    pub fn is_reserved(&self, token_id: &TokenId) -> bool {
+        self.reserved_names.contains(token_id)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Reserves `names` so that only the owner can hand them out with
+    /// `admin_register`.
+    #[payable]
+    pub fn add_reserved_names(&mut self, names: Vec<String>) {
+        assert_one_yocto();
+        Self::require_role(&Role::Moderator);
+        for name in names {
+            let token_id = self.assert_valid_name(&name);
+            self.reserved_names.insert(&token_id);
+        }
+    }
+
+    #[payable]
+    pub fn remove_reserved_names(&mut self, names: Vec<String>) {
+        assert_one_yocto();
+        Self::require_role(&Role::Moderator);
+        for name in names {
+            self.reserved_names.remove(&name.trim().to_lowercase());
+        }
+    }
+
+    pub fn is_reserved_name(&self, name: String) -> bool {
+        self.is_reserved(&name.trim().to_lowercase())
+    }
+
+    pub fn reserved_names(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<TokenId> {
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+        self.reserved_names.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+
+    /// Mints `token_id` to `token_owner_id` until `expired_date` without
+    /// payment. Works for reserved and regular names that are available.
+    #[payable]
+    pub fn admin_register(&mut self, token_id: TokenId, token_owner_id: AccountId, expired_date: U64) -> Token {
+        assert_one_yocto();
+        Self::require_role(&Role::Moderator);
+        let token_id = self.assert_valid_name(&token_id);
+        assert_eq!(self.name_state_of(&token_id), NameState::Available, "Name not available");
+        let now = env::block_timestamp_ms();
+        assert!(expired_date.0 > now, "Invalid expired date");
+        let token_metadata = self.metadata_by_token(&token_id);
+        self.internal_register_name(token_id, token_owner_id, token_metadata, expired_date.0 - now, 0, None)
+    }
+}
@@ -1,57 +1,210 @@
 const EXTRA_BYTES: usize = 10000;
+const UPGRADE_GAS: Gas = Gas(150_000_000_000_000);
+const PAUSE_GAS: Gas = Gas(5_000_000_000_000);
+/// Resolvers paused per call of `pause_resolvers`, so that the calls fit
+/// in the gas of one transaction.
+const MAX_PAUSE_BATCH: u64 = 40;
 
-use near_sdk::{ext_contract};
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::{ext_contract, assert_one_yocto};
 
 use crate::*;
 
 #[derive(Serialize, Deserialize, BorshDeserialize, BorshSerialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct ResolverArgs {
-    owner_id: AccountId
+    owner_id: AccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u32>
 }
 
 #[ext_contract(name_resolver)]
 pub trait NameResolver {
     fn owner_changed(&mut self, owner_id: AccountId) -> AccountId;
+    fn pause(&mut self);
+    fn unpause(&mut self);
+    fn upgrade(&mut self, version: u32, code: Base64VecU8) -> Promise;
+}
+
+impl Contract {
+    fn for_each_resolver(&self, from_index: Option<U128>, limit: Option<u64>, call: impl Fn(AccountId)) -> u64 {
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+        let limit = limit.unwrap_or(MAX_PAUSE_BATCH).min(MAX_PAUSE_BATCH);
+        let mut sent = 0;
+        for (token_id, _) in self.tokens.owner_by_id.iter().skip(start as usize).take(limit as usize) {
+            if !self.is_subname(&token_id) {
+                call(self.resolver_account_id(&token_id));
+                sent += 1;
+            }
+        }
+        sent
+    }
 }
 
 #[near_bindgen]
 impl Contract {
 
-    pub fn get_min_attach_balance(&self, args: &ResolverArgs) -> Balance {
-        ((RESOLVER_WASM_CODE.len() + EXTRA_BYTES + args.try_to_vec().unwrap().len() * 2) as Balance
-            * STORAGE_PRICE_PER_BYTE + 5)
-            .into()
+    pub fn get_min_attach_balance(&self, args: &ResolverArgs, code_size: usize) -> Balance {
+        (code_size + EXTRA_BYTES + args.try_to_vec().unwrap().len() * 2) as Balance
+            * STORAGE_PRICE_PER_BYTE + 5
     }
 
+    /// Deploys the resolver of `token_id` with the code of `version`, the
+    /// latest uploaded version by default, or the embedded version 1.
     #[payable]
-    pub fn setup(&mut self, token_id: TokenId) -> Promise {
+    pub fn setup(&mut self, token_id: TokenId, version: Option<u32>) -> Promise {
+        Self::require_unpaused();
+        let token_id = self.assert_valid_token_id(&token_id);
         let token = self.token_owner_only(&token_id);
+        assert!(!self.is_subname(&token_id), "Subnames cannot have a resolver");
         let owner_id = token.owner_id;
+        let (version, code) = self.resolver_code(version);
         let resolver_args = ResolverArgs {
-            owner_id: owner_id.to_owned()
+            owner_id: owner_id.to_owned(),
+            version: Some(version)
         };
-        let min_attach_balance = self.get_min_attach_balance(&resolver_args);
+        let min_attach_balance = self.get_min_attach_balance(&resolver_args, code.len());
         let deposited = env::attached_deposit();
         assert!(deposited > min_attach_balance, "Deposited not enough balance");
-        let resolver_account_id = AccountId::try_from(format!("{}.{}", token_id, env::current_account_id())).unwrap();
+        let resolver_account_id = self.resolver_account_id(&token_id);
         Promise::new(resolver_account_id)
             .create_account()
             .transfer(min_attach_balance)
-            .deploy_contract(RESOLVER_WASM_CODE.to_vec())
+            .deploy_contract(code)
             .function_call("new".to_owned(), serde_json::to_vec(&resolver_args).unwrap(), 0, GAS).then(
-                Self::ext(env::current_account_id()).failure_resolve(owner_id, deposited)
+                Self::ext(env::current_account_id()).on_resolver_setup(token_id, owner_id, deposited, version)
             )
     }
 
+    #[private]
+    pub fn on_resolver_setup(&mut self, token_id: TokenId, owner_id: AccountId, deposited: Balance, version: u32) {
+        if let PromiseResult::Failed = env::promise_result(0) {
+            Promise::new(owner_id).transfer(deposited);
+            return;
+        }
+        self.internal_set_resolver_version(&token_id, version);
+        NnsEvent::ResolverDeploy(vec![ResolverData {
+            resolver_id: self.resolver_account_id(&token_id),
+            token_id,
+            owner_id,
+            version: Some(version),
+        }]).emit();
+    }
+
+    /// Deploys the code of `version` on the resolver of `token_id` and
+    /// migrates its state. The attached deposit goes with the upgrade call
+    /// to pay for a larger code. The resolver returns it when the upgrade
+    /// fails and it is refunded to the owner. Resolvers deployed before
+    /// versioning have no recorded version and no `upgrade` method, so they
+    /// are rejected.
+    #[payable]
+    pub fn upgrade_resolver(&mut self, token_id: TokenId, version: u32) -> Promise {
+        Self::require_unpaused();
+        let token_id = self.assert_valid_token_id(&token_id);
+        let owner_id = self.token_owner_only(&token_id).owner_id;
+        let current_version = self.resolver_version_by_name.get(&token_id)
+            .expect("Resolver predates versioning and cannot be upgraded");
+        assert!(version > current_version, "Resolver already on a newer version");
+        let (version, code) = self.resolver_code(Some(version));
+        let deposited = env::attached_deposit();
+        name_resolver::ext(self.resolver_account_id(&token_id))
+            .with_static_gas(UPGRADE_GAS)
+            .with_attached_deposit(deposited)
+            .upgrade(version, code.into())
+            .then(Self::ext(env::current_account_id()).on_resolver_upgrade(token_id, owner_id, version, U128::from(deposited)))
+    }
+
+    /// Records the new version, or refunds the deposit to the owner when the
+    /// upgrade failed.
+    #[private]
+    pub fn on_resolver_upgrade(&mut self, token_id: TokenId, owner_id: AccountId, version: u32, deposited: U128) -> bool {
+        let upgraded = match env::promise_result(0) {
+            PromiseResult::Successful(result) => serde_json::from_slice::<bool>(&result).unwrap_or(false),
+            _ => false,
+        };
+        if !upgraded {
+            env::log_str(&format!("Upgrade of {} to version {} failed", token_id, version));
+            if deposited.0 > 0 {
+                Promise::new(owner_id).transfer(deposited.0);
+            }
+            return false;
+        }
+        self.internal_set_resolver_version(&token_id, version);
+        NnsEvent::ResolverUpgrade(vec![ResolverData {
+            resolver_id: self.resolver_account_id(&token_id),
+            token_id,
+            owner_id,
+            version: Some(version),
+        }]).emit();
+        true
+    }
+
     pub fn take_ownership(&mut self, token_id: TokenId) -> Promise {
+        Self::require_unpaused();
+        let token_id = self.assert_valid_token_id(&token_id);
         self.token_owner_only(&token_id);
+        assert!(!self.is_subname(&token_id), "Subnames cannot have a resolver");
         let account_id = env::predecessor_account_id();
         let resolver_args = ResolverArgs {
-            owner_id: account_id.to_owned()
+            owner_id: account_id.to_owned(),
+            version: None
         };
-        let resolver_account_id = AccountId::try_from(format!("{}.{}", token_id, env::current_account_id())).unwrap();
+        let resolver_account_id = self.resolver_account_id(&token_id);
         Promise::new(resolver_account_id)
-            .function_call("owner_changed".to_owned(), serde_json::to_vec(&resolver_args).unwrap(), 0, GAS)
+            .function_call("owner_changed".to_owned(), serde_json::to_vec(&resolver_args).unwrap(), 0, GAS).then(
+                Self::ext(env::current_account_id()).on_ownership_synced(token_id, account_id)
+            )
     }
-}
\ No newline at end of file
+
+    #[private]
+    pub fn on_ownership_synced(&mut self, token_id: TokenId, owner_id: AccountId) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            NnsEvent::OwnershipSync(vec![ResolverData {
+                resolver_id: self.resolver_account_id(&token_id),
+                token_id,
+                owner_id,
+                version: None,
+            }]).emit();
+        }
+    }
+
+    /// Halts record updates on the resolver of `token_id`.
+    #[payable]
+    pub fn pause_resolver(&mut self, token_id: TokenId) -> Promise {
+        assert_one_yocto();
+        Self::require_role(&Role::Moderator);
+        name_resolver::ext(self.resolver_account_id(&token_id))
+            .with_static_gas(GAS)
+            .pause()
+    }
+
+    #[payable]
+    pub fn unpause_resolver(&mut self, token_id: TokenId) -> Promise {
+        assert_one_yocto();
+        Self::require_role(&Role::Moderator);
+        name_resolver::ext(self.resolver_account_id(&token_id))
+            .with_static_gas(GAS)
+            .unpause()
+    }
+
+    /// Halts the resolvers of up to `limit` names from `from_index`, to stop
+    /// every resolver in a few calls. Names without a resolver only fail
+    /// their own call. Returns how many calls were sent.
+    #[payable]
+    pub fn pause_resolvers(&mut self, from_index: Option<U128>, limit: Option<u64>) -> u64 {
+        assert_one_yocto();
+        Self::require_role(&Role::Moderator);
+        self.for_each_resolver(from_index, limit, |resolver_id| {
+            name_resolver::ext(resolver_id).with_static_gas(PAUSE_GAS).pause();
+        })
+    }
+
+    #[payable]
+    pub fn unpause_resolvers(&mut self, from_index: Option<U128>, limit: Option<u64>) -> u64 {
+        assert_one_yocto();
+        Self::require_role(&Role::Moderator);
+        self.for_each_resolver(from_index, limit, |resolver_id| {
+            name_resolver::ext(resolver_id).with_static_gas(PAUSE_GAS).unpause();
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use near_contract_standards::non_fungible_token::refund_deposit;
+use near_sdk::assert_one_yocto;
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8};
+
+use crate::*;
+
+/// Resolver built with the registry, served as version 1 so that `setup`
+/// works before any upload.
+const EMBEDDED_RESOLVER_CODE: &[u8] = include_bytes!("../../out/resolver.wasm");
+pub const EMBEDDED_RESOLVER_VERSION: u32 = 1;
+
+/// A resolver wasm uploaded to the registry. The code itself is kept in
+/// `resolver_codes` so that listing versions stays cheap.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolverVersion {
+    pub hash: Base58CryptoHash,
+    pub size: U64,
+    pub uploaded_at: U64,
+    /// Paid for the storage of the code, refunded on removal.
+    pub uploader_id: AccountId,
+}
+
+impl Contract {
+    /// `version`, or the latest version when `None`, with its code.
+    pub fn resolver_code(&self, version: Option<u32>) -> (u32, Vec<u8>) {
+        let version = version.unwrap_or_else(|| self.latest_resolver_version());
+        if version == EMBEDDED_RESOLVER_VERSION {
+            return (version, EMBEDDED_RESOLVER_CODE.to_vec());
+        }
+        let code = self.resolver_codes.get(&version).expect("Resolver version not found");
+        (version, code)
+    }
+
+    /// Records that the resolver of `token_id` runs `version`, so that the
+    /// code of a version in use cannot be removed.
+    pub fn internal_set_resolver_version(&mut self, token_id: &TokenId, version: u32) {
+        if let Some(previous) = self.resolver_version_by_name.insert(token_id, &version) {
+            let count = self.resolvers_on_version(previous);
+            self.resolver_count_by_version.insert(&previous, &count.saturating_sub(1));
+        }
+        let count = self.resolvers_on_version(version);
+        self.resolver_count_by_version.insert(&version, &(count + 1));
+    }
+
+    /// Forgets the resolver version of `token_id`, when the name is burned
+    /// or handed to a new owner.
+    pub fn internal_remove_resolver_version(&mut self, token_id: &TokenId) {
+        if let Some(version) = self.resolver_version_by_name.remove(token_id) {
+            let count = self.resolvers_on_version(version);
+            self.resolver_count_by_version.insert(&version, &count.saturating_sub(1));
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Stores a new resolver `version`. `hash` is the sha256 of `code` and is
+    /// checked before storing. The storage is paid by the attached deposit.
+    #[payable]
+    pub fn upload_resolver_code(&mut self, version: u32, hash: Base58CryptoHash, code: Base64VecU8) -> ResolverVersion {
+        require!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+        Self::require_role(&Role::Upgrader);
+        assert!(version > EMBEDDED_RESOLVER_VERSION, "Version 1 is the embedded resolver");
+        assert!(self.resolver_versions.get(&version).is_none(), "Resolver version exists");
+        let code: Vec<u8> = code.into();
+        assert_eq!(env::sha256_array(&code), CryptoHash::from(hash), "Hash does not match the code");
+        let initial_storage_usage = env::storage_usage();
+        let resolver_version = ResolverVersion {
+            hash,
+            size: U64(code.len() as u64),
+            uploaded_at: U64(env::block_timestamp_ms()),
+            uploader_id: env::predecessor_account_id(),
+        };
+        self.resolver_codes.insert(&version, &code);
+        self.resolver_versions.insert(&version, &resolver_version);
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+        resolver_version
+    }
+
+    /// Deletes the code of `version` and refunds its storage to its uploader.
+    /// Only versions that no resolver runs can be removed, so that they stay
+    /// upgradable.
+    #[payable]
+    pub fn remove_resolver_code(&mut self, version: u32) -> Promise {
+        assert_one_yocto();
+        Self::require_role(&Role::Upgrader);
+        assert_eq!(self.resolvers_on_version(version), 0, "Resolvers still run this version");
+        let initial_storage_usage = env::storage_usage();
+        let resolver_version = self.resolver_versions.remove(&version).expect("Resolver version not found");
+        self.resolver_codes.remove(&version);
+        self.resolver_count_by_version.remove(&version);
+        let storage_released = initial_storage_usage - env::storage_usage();
+        Promise::new(resolver_version.uploader_id).transfer(Balance::from(storage_released) * env::storage_byte_cost())
+    }
+
+    /// Uploaded versions. The embedded version 1 is not listed.
+    pub fn resolver_versions(&self) -> Vec<(u32, ResolverVersion)> {
+        self.resolver_versions.iter().collect()
+    }
+
+    pub fn latest_resolver_version(&self) -> u32 {
+        self.resolver_versions.max().unwrap_or(EMBEDDED_RESOLVER_VERSION)
+    }
+
+    /// Number of resolvers deployed or upgraded with `version`.
+    pub fn resolvers_on_version(&self, version: u32) -> u64 {
+        self.resolver_count_by_version.get(&version).unwrap_or(0)
+    }
+
+    /// Version of the code deployed on the resolver of `token_id`.
+    pub fn resolver_version_of(&self, token_id: TokenId) -> Option<u32> {
+        self.resolver_version_by_name.get(&token_id)
+    }
+}
@@ -0,0 +1,56 @@
+use near_sdk::assert_one_yocto;
+use near_sdk_contract_tools::rbac::Rbac;
+
+use crate::*;
+
+#[derive(BorshSerialize, BorshStorageKey, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Sets prices, fees, premiums, accepted tokens and referral rates.
+    PricingManager,
+    /// Withdraws NEAR and token revenue.
+    Treasurer,
+    /// Manages reserved names, mints them with `admin_register` and pauses
+    /// the registry and resolvers.
+    Moderator,
+    /// Deploys new registry code with `upgrade`, which runs `migrate`.
+    Upgrader,
+}
+
+pub const ROLES: [Role; 4] = [Role::PricingManager, Role::Treasurer, Role::Moderator, Role::Upgrader];
+
+impl Contract {
+    pub fn grant_all_roles(&mut self, account_id: &AccountId) {
+        for role in ROLES {
+            self.add_role(account_id.to_owned(), &role);
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn grant_role(&mut self, role: Role, account_id: AccountId) {
+        assert_one_yocto();
+        Self::require_owner();
+        self.add_role(account_id, &role);
+    }
+
+    #[payable]
+    pub fn revoke_role(&mut self, role: Role, account_id: AccountId) {
+        assert_one_yocto();
+        Self::require_owner();
+        self.remove_role(&account_id, &role);
+    }
+
+    pub fn roles_of(&self, account_id: AccountId) -> Vec<Role> {
+        ROLES.into_iter()
+            .filter(|role| Self::has_role(&account_id, role))
+            .collect()
+    }
+
+    pub fn role_members(&self, role: Role) -> Vec<AccountId> {
+        Self::iter_members_of(&role).collect()
+    }
+}
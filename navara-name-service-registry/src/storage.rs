@@ -0,0 +1,146 @@
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement};
+use near_sdk::{json_types::U128, assert_one_yocto, StorageUsage};
+
+use crate::*;
+
+/// Bytes of a storage balance entry for the longest account id: the record
+/// overhead, the key prefix, the account id and the balance.
+pub const ACCOUNT_STORAGE_BYTES: StorageUsage = 40 + 1 + 4 + 64 + 16;
+
+impl Contract {
+    /// NEAR locked by every registered account for its own entry.
+    pub fn min_storage_balance() -> Balance {
+        Balance::from(ACCOUNT_STORAGE_BYTES) * env::storage_byte_cost()
+    }
+
+    pub fn storage_balance(&self, account_id: &AccountId) -> Balance {
+        self.storage_balances.get(account_id).unwrap_or(0)
+    }
+
+    /// Part of the storage balance that can pay for names.
+    pub fn storage_available(&self, account_id: &AccountId) -> Balance {
+        self.storage_balance(account_id).saturating_sub(Self::min_storage_balance())
+    }
+
+    fn internal_storage_deposit(&mut self, account_id: &AccountId, amount: Balance) {
+        let balance = self.storage_balance(account_id);
+        self.storage_balances.insert(account_id, &(balance + amount));
+        self.total_storage_balance += amount;
+    }
+
+    /// Withdraws from the available balance, the minimum stays locked.
+    fn internal_storage_withdraw(&mut self, account_id: &AccountId, amount: Balance) {
+        assert!(amount <= self.storage_available(account_id), "Not enough storage balance");
+        let balance = self.storage_balance(account_id);
+        self.storage_balances.insert(account_id, &(balance - amount));
+        self.total_storage_balance -= amount;
+    }
+
+    /// Charges `account_id` for `storage_used` bytes, first from `deposit`
+    /// and then from its storage balance. Returns what is left of `deposit`.
+    pub fn charge_storage(&mut self, account_id: &AccountId, storage_used: StorageUsage, deposit: Balance) -> Balance {
+        let cost = Balance::from(storage_used) * env::storage_byte_cost();
+        if cost <= deposit {
+            return deposit - cost;
+        }
+        self.internal_storage_withdraw(account_id, cost - deposit);
+        0
+    }
+
+    /// Returns `amount` of freed storage to `account_id`: to its storage
+    /// balance when it is registered, or by transfer.
+    pub fn credit_storage(&mut self, account_id: &AccountId, amount: Balance) {
+        if amount == 0 {
+            return;
+        }
+        if self.storage_balances.contains_key(account_id) {
+            self.internal_storage_deposit(account_id, amount);
+        } else {
+            Promise::new(account_id.to_owned()).transfer(amount);
+        }
+    }
+
+    /// Records `account_id` as the payer of the storage of `token_id`, unless
+    /// a released name kept the storage of an earlier payer.
+    pub fn internal_set_storage_payer(&mut self, token_id: &TokenId, account_id: &AccountId) {
+        if !self.storage_payers.contains_key(token_id) {
+            self.storage_payers.insert(token_id, account_id);
+        }
+    }
+
+    fn storage_balance_view(&self, account_id: &AccountId) -> Option<StorageBalance> {
+        self.storage_balances.get(account_id).map(|balance| StorageBalance {
+            total: U128::from(balance),
+            available: U128::from(balance.saturating_sub(Self::min_storage_balance())),
+        })
+    }
+}
+
+/// Prepaid NEAR for the storage of names and primary names. Registration
+/// locks the storage of the account entry, the rest is charged when it is
+/// used and credited back when it is freed.
+#[near_bindgen]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance {
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let deposit = env::attached_deposit();
+        let registered = self.storage_balances.contains_key(&account_id);
+        let min = Self::min_storage_balance();
+        if !registered {
+            assert!(deposit >= min, "The attached deposit is less than the minimum storage balance");
+        }
+        let amount = match (registration_only.unwrap_or(false), registered) {
+            (true, true) => 0,
+            (true, false) => min,
+            (false, _) => deposit,
+        };
+        self.internal_storage_deposit(&account_id, amount);
+        if deposit > amount {
+            Promise::new(env::predecessor_account_id()).transfer(deposit - amount);
+        }
+        self.storage_balance_view(&account_id).unwrap()
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let amount = amount.map(u128::from).unwrap_or_else(|| self.storage_available(&account_id));
+        self.internal_storage_withdraw(&account_id, amount);
+        if amount > 0 {
+            Promise::new(account_id.to_owned()).transfer(amount);
+        }
+        self.storage_balance_view(&account_id).unwrap_or(StorageBalance {
+            total: U128(0),
+            available: U128(0),
+        })
+    }
+
+    #[payable]
+    fn storage_unregister(&mut self, #[allow(unused_variables)] force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        match self.storage_balances.remove(&account_id) {
+            Some(balance) => {
+                self.total_storage_balance -= balance;
+                if balance > 0 {
+                    Promise::new(account_id).transfer(balance);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128::from(Self::min_storage_balance()),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_balance_view(&account_id)
+    }
+}
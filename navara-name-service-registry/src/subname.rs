@@ -0,0 +1,157 @@
+use near_contract_standards::non_fungible_token::events::NftBurn;
+use near_contract_standards::non_fungible_token::refund_deposit;
+use near_sdk::assert_one_yocto;
+
+use crate::*;
+
+impl Contract {
+    pub fn is_subname(&self, token_id: &TokenId) -> bool {
+        self.parent_name.get(token_id).is_some()
+    }
+
+    fn parent_owner_only(&self, token_id: &TokenId) -> TokenId {
+        let parent_id = self.parent_name.get(token_id).expect("Not a subname");
+        self.token_owner_only(&parent_id);
+        parent_id
+    }
+
+    /// Stored expiry of a subname. `None` follows the parent, and any expiry
+    /// is capped by the parent when read, see `expired_date_of`.
+    fn subname_expired_date(expired_date: Option<U64>) -> u64 {
+        expired_date.map(u64::from).unwrap_or(u64::MAX)
+    }
+
+    /// Refunds the offers on `token_id` and removes the primary name it
+    /// backs, so that the storage measured around `internal_burn` is only
+    /// the storage of the name.
+    pub fn internal_settle_before_burn(&mut self, token_id: &TokenId) {
+        self.internal_refund_offers(token_id);
+        if let Some(owner_id) = self.tokens.owner_by_id.get(token_id) {
+            if self.default_name.get(&owner_id).as_ref() == Some(token_id) {
+                self.internal_remove_default(&owner_id);
+            }
+        }
+    }
+
+    /// Removes `token_id` and everything the registry keeps about it,
+    /// returning the account that owned it.
+    pub fn internal_burn(&mut self, token_id: &TokenId) -> AccountId {
+        let owner_id = self.tokens.owner_by_id.remove(token_id).expect("Token not found");
+        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+            let mut owner_tokens = tokens_per_owner.get(&owner_id).unwrap();
+            owner_tokens.remove(token_id);
+            if owner_tokens.is_empty() {
+                tokens_per_owner.remove(&owner_id);
+            } else {
+                tokens_per_owner.insert(&owner_id, &owner_tokens);
+            }
+        }
+        if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
+            token_metadata_by_id.remove(token_id);
+        }
+        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+            approvals_by_id.remove(token_id);
+        }
+        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
+            next_approval_id_by_id.remove(token_id);
+        }
+        if self.default_name.get(&owner_id).as_ref() == Some(token_id) {
+            self.internal_remove_default(&owner_id);
+        }
+        self.name_expired_date.remove(token_id);
+        self.registrants.remove(token_id);
+        self.storage_payers.remove(token_id);
+        self.internal_remove_resolver_version(token_id);
+        self.internal_refund_offers(token_id);
+        if let Some(parent_id) = self.parent_name.remove(token_id) {
+            let mut subnames = self.subnames.get(&parent_id).unwrap_or_default();
+            subnames.retain(|subname| subname != token_id);
+            if subnames.is_empty() {
+                self.subnames.remove(&parent_id);
+            } else {
+                self.subnames.insert(&parent_id, &subnames);
+            }
+        }
+        NftBurn {
+            owner_id: &owner_id,
+            token_ids: &[token_id],
+            authorized_id: None,
+            memo: None,
+        }.emit();
+        owner_id
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Mints `<label>.<parent_id>` to `owner_id`. Only the parent owner can
+    /// create subnames and they never outlive the parent.
+    #[payable]
+    pub fn create_subname(
+        &mut self,
+        parent_id: TokenId,
+        label: String,
+        owner_id: AccountId,
+        expired_date: Option<U64>
+    ) -> Token {
+        Self::require_unpaused();
+        let parent_id = self.assert_valid_name(&parent_id);
+        self.token_owner_only(&parent_id);
+        assert!(!self.is_subname(&parent_id), "Nested subnames are not supported");
+        self.asset_name_expired(&parent_id);
+        let token_id = self.normalize_subname(&parent_id, &label).unwrap_or_else(|err| panic!("{}", err));
+        assert!(self.nft_token(token_id.to_owned()).is_none(), "Subname exists");
+
+        let initial_storage_usage = env::storage_usage();
+        let new_expired_date = Self::subname_expired_date(expired_date);
+        self.update_expired_date(&token_id, &new_expired_date);
+        self.parent_name.insert(&token_id, &parent_id);
+        let mut subnames = self.subnames.get(&parent_id).unwrap_or_default();
+        subnames.push(token_id.to_owned());
+        self.subnames.insert(&parent_id, &subnames);
+        let token_metadata = self.metadata_by_token(&token_id);
+        self.internal_set_storage_payer(&token_id, &env::predecessor_account_id());
+        let token = self.tokens.internal_mint_with_refund(token_id, owner_id, Some(token_metadata), None);
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+        NnsEvent::SubnameCreate(vec![SubnameData {
+            token_id: token.token_id.to_owned(),
+            parent_id,
+            owner_id: token.owner_id.to_owned(),
+        }]).emit();
+        token
+    }
+
+    /// Moves the expiry of a subname, still capped at the parent expiry.
+    pub fn renew_subname(&mut self, token_id: TokenId, expired_date: Option<U64>) {
+        let token_id = self.assert_valid_token_id(&token_id);
+        self.parent_owner_only(&token_id);
+        let new_expired_date = Self::subname_expired_date(expired_date);
+        self.update_expired_date(&token_id, &new_expired_date);
+    }
+
+    /// Burns a subname and refunds its storage to the parent owner.
+    #[payable]
+    pub fn revoke_subname(&mut self, token_id: TokenId) -> Promise {
+        assert_one_yocto();
+        let token_id = self.assert_valid_token_id(&token_id);
+        let parent_id = self.parent_owner_only(&token_id);
+        self.internal_settle_before_burn(&token_id);
+        let initial_storage_usage = env::storage_usage();
+        let owner_id = self.internal_burn(&token_id);
+        NnsEvent::SubnameRevoke(vec![SubnameData {
+            token_id,
+            parent_id,
+            owner_id,
+        }]).emit();
+        let storage_released = initial_storage_usage - env::storage_usage();
+        Promise::new(env::predecessor_account_id()).transfer(Balance::from(storage_released) * env::storage_byte_cost())
+    }
+
+    pub fn parent_of(&self, token_id: TokenId) -> Option<TokenId> {
+        self.parent_name.get(&token_id)
+    }
+
+    pub fn subnames_of(&self, parent_id: TokenId) -> Vec<TokenId> {
+        self.subnames.get(&parent_id).unwrap_or_default()
+    }
+}
@@ -0,0 +1,81 @@
+use crate::*;
+
+/// Background colors by name length: 1-3, 4, 5-6 and 7+ characters.
+const LENGTH_STYLES: [(usize, &str, &str); 4] = [
+    (3, "#f7b733", "#fc4a1a"),
+    (4, "#8e2de2", "#4a00e0"),
+    (6, "#00b4db", "#0083b0"),
+    (usize::MAX, "#3a4a5c", "#1e2833"),
+];
+
+/// `yyyy-mm-dd` of a Unix timestamp in milliseconds.
+fn format_date(timestamp_ms: u64) -> String {
+    // Civil date from days since 1970-01-01, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (timestamp_ms / ONE_DAY_MILLISECOND) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `text` with the characters XML gives a meaning to escaped.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Artwork of `token_id`: the name on a background picked by its length,
+/// with the expiry date when known.
+pub fn name_svg(token_id: &TokenId, expired_date: Option<u64>) -> String {
+    let length = token_id.chars().count();
+    let (_, from, to) = LENGTH_STYLES.iter().find(|(max_length, _, _)| length <= *max_length).unwrap();
+    let font_size = (420 / length.max(1)).clamp(14, 64);
+    let expiry = expired_date
+        .map(|expired_date| format!("<text x='24' y='264' font-size='14' fill='#ffffff' opacity='0.8'>expires {}</text>", format_date(expired_date)))
+        .unwrap_or_default();
+    format!(
+        "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'>\
+<defs><linearGradient id='b' x1='0' y1='0' x2='1' y2='1'><stop offset='0' stop-color='{}'/><stop offset='1' stop-color='{}'/></linearGradient></defs>\
+<rect width='288' height='288' rx='24' fill='url(#b)'/>\
+<g font-family='sans-serif' font-weight='bold'>\
+<text x='24' y='48' font-size='18' fill='#ffffff'>NNS</text>\
+<text x='24' y='160' font-size='{}' fill='#ffffff'>{}.nns</text>\
+{}</g></svg>",
+        from, to, font_size, escape_xml(token_id), expiry
+    )
+}
+
+/// `name_svg` as a `data:` URL usable as `TokenMetadata.media`.
+pub fn name_svg_data_url(token_id: &TokenId, expired_date: Option<u64>) -> String {
+    let svg = name_svg(token_id, expired_date)
+        .replace('%', "%25")
+        .replace('#', "%23")
+        .replace('<', "%3C")
+        .replace('>', "%3E");
+    format!("data:image/svg+xml,{}", svg)
+}
+
+#[near_bindgen]
+impl Contract {
+    /// SVG artwork of `token_id`, regenerated from its current expiry.
+    pub fn nft_svg(&self, token_id: TokenId) -> Option<String> {
+        self.tokens.owner_by_id.get(&token_id)?;
+        Some(name_svg(&token_id, self.expired_date_of(&token_id)))
+    }
+}
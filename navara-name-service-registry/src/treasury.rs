@@ -0,0 +1,114 @@
+use near_sdk::{json_types::U128, assert_one_yocto};
+
+use crate::*;
+
+pub const REVENUE_PERIOD_MILLISECOND: u64 = 30 * ONE_DAY_MILLISECOND;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Revenue {
+    pub registration: U128,
+    pub renewal: U128,
+    pub refunds: U128,
+    pub referrals: U128,
+    pub royalties: U128,
+    pub withdrawn: U128,
+}
+
+impl Default for Revenue {
+    fn default() -> Self {
+        Self {
+            registration: U128(0),
+            renewal: U128(0),
+            refunds: U128(0),
+            referrals: U128(0),
+            royalties: U128(0),
+            withdrawn: U128(0),
+        }
+    }
+}
+
+pub enum RevenueKind {
+    Registration,
+    Renewal,
+    Refund,
+    Referral,
+    Royalty,
+    Withdrawal,
+}
+
+impl Revenue {
+    fn add(&mut self, kind: &RevenueKind, amount: Balance) {
+        let entry = match kind {
+            RevenueKind::Registration => &mut self.registration,
+            RevenueKind::Renewal => &mut self.renewal,
+            RevenueKind::Refund => &mut self.refunds,
+            RevenueKind::Referral => &mut self.referrals,
+            RevenueKind::Royalty => &mut self.royalties,
+            RevenueKind::Withdrawal => &mut self.withdrawn,
+        };
+        entry.0 += amount;
+    }
+}
+
+impl Contract {
+    pub fn current_revenue_period(&self) -> u64 {
+        env::block_timestamp_ms() / REVENUE_PERIOD_MILLISECOND
+    }
+
+    pub fn record_revenue(&mut self, kind: RevenueKind, amount: Balance) {
+        if amount == 0 {
+            return;
+        }
+        self.total_revenue.add(&kind, amount);
+        let period = self.current_revenue_period();
+        let mut revenue = self.revenue_by_period.get(&period).unwrap_or_default();
+        revenue.add(&kind, amount);
+        self.revenue_by_period.insert(&period, &revenue);
+    }
+
+    /// Balance that has to stay on the account to pay for its storage,
+    /// the referral rewards not claimed yet, prepaid storage balances and
+    /// escrowed offers.
+    pub fn locked_balance(&self) -> Balance {
+        Balance::from(env::storage_usage()) * env::storage_byte_cost()
+            + self.referral_liability
+            + self.total_storage_balance
+            + self.total_offers
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn withdraw(&mut self, amount: U128, beneficiary: AccountId) -> Promise {
+        assert_one_yocto();
+        Self::require_role(&Role::Treasurer);
+        let amount = u128::from(amount);
+        assert!(amount <= self.available_balance().0, "Not enough available balance");
+        self.record_revenue(RevenueKind::Withdrawal, amount);
+        Promise::new(beneficiary).transfer(amount)
+    }
+
+    pub fn available_balance(&self) -> U128 {
+        U128::from(env::account_balance().saturating_sub(self.locked_balance()))
+    }
+
+    pub fn revenue(&self) -> Revenue {
+        self.total_revenue
+    }
+
+    pub fn revenue_period(&self) -> U64 {
+        U64::from(REVENUE_PERIOD_MILLISECOND)
+    }
+
+    /// Revenue per period, keyed by `block_timestamp_ms / revenue_period`.
+    pub fn revenue_by_period(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<(U64, Revenue)> {
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+        self.revenue_by_period.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(period, revenue)| (U64::from(period), revenue))
+            .collect()
+    }
+}
@@ -3,10 +3,12 @@ use crate::*;
 #[near_bindgen]
 impl Contract {
     pub fn set_ipfs(&mut self, value: String) {
+        Self::require_unpaused();
         Self::require_owner();
         self.ipfs.set(&value);
     }
 
+    #[allow(clippy::needless_return)]
     pub fn ipfs(&self) -> Option<String> {
         return self.ipfs.get()
     }
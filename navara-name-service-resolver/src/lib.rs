@@ -8,16 +8,23 @@ use near_sdk::{
     env, near_bindgen, require, AccountId, BorshStorageKey, PanicOnDefault, Promise, Balance
 };
 use near_sdk_contract_tools::owner::OwnerExternal;
-use near_sdk_contract_tools::{owner::Owner, Owner};
+// The exported pause methods call this trait.
+#[cfg(target_arch = "wasm32")]
+use near_sdk_contract_tools::pause::PauseExternal;
+use near_sdk_contract_tools::{owner::Owner, pause::Pause, Owner, Pause};
 
+#[allow(unused_imports)]
 pub use crate::resolver::*;
+#[allow(unused_imports)]
 pub use crate::ipfs::*;
+#[allow(unused_imports)]
 pub use crate::text_records::*;
 
 
 mod resolver; 
 mod ipfs;
 mod text_records;
+mod upgrade;
 
 //The Json token is what will be returned from view calls. 
 #[derive(Serialize, Deserialize)]
@@ -28,13 +35,14 @@ pub struct JsonToken {
 }
 
 #[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault, Owner)]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault, Owner, Pause)]
 pub struct Contract {
     registry: AccountId,
     address_by_networks: UnorderedMap<String, String>,
     ipfs: LazyOption<String>,
     text_records: HashMap<String, String>,
     icon: LazyOption<String>,
+    version: u32,
 }
 
 const DATA_IMAGE_SVG_NAVARA_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
@@ -50,7 +58,7 @@ enum StorageKey {
 impl Contract {
     
     #[init]
-    pub fn new(owner_id: AccountId) -> Self {
+    pub fn new(owner_id: AccountId, version: Option<u32>) -> Self {
         require!(!env::state_exists(), "Already initialized");
         let registry = env::predecessor_account_id();
         
@@ -60,6 +68,7 @@ impl Contract {
             ipfs: LazyOption::new(StorageKey::Ipfs, None),
             text_records: HashMap::new(),
             icon: LazyOption::new(StorageKey::Icon, Some(&DATA_IMAGE_SVG_NAVARA_ICON.to_owned())),
+            version: version.unwrap_or(0),
         };
         Owner::init(&mut contract, &owner_id);
         contract
@@ -69,7 +78,19 @@ impl Contract {
         assert_eq!(env::predecessor_account_id(), self.registry, "Only registry")
     }
 
+    /// Halts record updates. Only the registry can pause its resolvers.
+    pub fn pause(&mut self) {
+        self.only_registry();
+        Pause::pause(self);
+    }
+
+    pub fn unpause(&mut self) {
+        self.only_registry();
+        Pause::unpause(self);
+    }
+
     pub fn clear(&mut self, beneficiary: AccountId) -> Promise {
+        Self::require_unpaused();
         Self::require_owner();
         let initial_storage_usage = env::storage_usage(); 
         self.address_by_networks.clear();
@@ -89,6 +110,7 @@ impl Contract {
     }
 
     pub fn self_delete(beneficiary: AccountId){
+        Self::require_unpaused();
         Self::require_owner();
         Promise::new(env::current_account_id())
             .delete_account(beneficiary);
@@ -96,6 +118,7 @@ impl Contract {
 }
 
 #[cfg(all(test, not(target_arch = "wasm32")))]
+#[allow(clippy::useless_conversion)]
 mod tests {
     use near_sdk::test_utils::{accounts, VMContextBuilder};
     use near_sdk::testing_env;
@@ -115,7 +138,7 @@ mod tests {
     fn test_new() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let contract = Contract::new(accounts(1).into());
+        let contract = Contract::new(accounts(1).into(), None);
         testing_env!(context.is_view(true).build());
         assert_eq!(contract.get_addresses(None, None).len(), 0);
     }
@@ -124,7 +147,7 @@ mod tests {
     fn test_add_addresses() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(accounts(1).into());
+        let mut contract = Contract::new(accounts(1).into(), None);
         let mut addresses = HashMap::new();
         let bitcoin = "bitcoin".to_string();
         let ethereum = "ethereum".to_string();
@@ -143,7 +166,7 @@ mod tests {
     fn test_add_addresses_panic() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(accounts(2).into());
+        let mut contract = Contract::new(accounts(2).into(), None);
         let mut addresses = HashMap::new();
         let ethereum = "ethereum".to_string();
         let ethereum_address = "0xB65B139A319A09F088486C22D18074810BA99715".to_string();
@@ -155,7 +178,7 @@ mod tests {
     fn test_add_ipfs() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(accounts(1).into());
+        let mut contract = Contract::new(accounts(1).into(), None);
         let ipfs = "bafybeighxhsavoanjqkqvnnpbkvoweurybjt7gauunbg37ueahcbze5ise".to_owned();
         contract.set_ipfs(ipfs.to_owned());
         assert_eq!(contract.ipfs().unwrap(), ipfs);
@@ -165,7 +188,7 @@ mod tests {
     fn test_add_record() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(accounts(1).into());
+        let mut contract = Contract::new(accounts(1).into(), None);
         let mut records = HashMap::new();
         let facebook = "facebook".to_string();
         let youtube = "youtube".to_string();
@@ -184,8 +207,110 @@ mod tests {
     fn test_owner_changed() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
-        let mut contract = Contract::new(accounts(1).into());
+        let mut contract = Contract::new(accounts(1).into(), None);
         contract.owner_changed(accounts(2));
     }
 
+    #[test]
+    #[should_panic(expected = "Disallowed while contract is paused")]
+    fn test_set_records_while_paused() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1), None);
+        contract.pause();
+        assert!(Contract::is_paused());
+        assert_eq!(contract.get_text_records().len(), 0);
+        contract.set_ipfs("bafybeighxhsavoanjqkqvnnpbkvoweurybjt7gauunbg37ueahcbze5ise".to_owned());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only registry")]
+    fn test_pause_only_registry() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(2), None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.pause();
+    }
+
+    #[test]
+    fn test_migrate() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), Some(1));
+        contract.set_ipfs("bafybeighxhsavoanjqkqvnnpbkvoweurybjt7gauunbg37ueahcbze5ise".to_owned());
+        env::state_write(&contract);
+        let contract = Contract::migrate(2);
+        assert_eq!(contract.version(), 2);
+        assert!(contract.ipfs().is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only registry")]
+    fn test_upgrade_only_registry() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(1), None);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.upgrade(1, vec![0].into());
+    }
+
+    #[test]
+    fn test_migrate_from_v0() {
+        #[derive(BorshSerialize)]
+        struct ContractV0 {
+            registry: AccountId,
+            address_by_networks: UnorderedMap<String, String>,
+            ipfs: LazyOption<String>,
+            text_records: HashMap<String, String>,
+            icon: LazyOption<String>,
+        }
+
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut address_by_networks = UnorderedMap::new(StorageKey::AddressByNetworks);
+        address_by_networks.insert(&"near".to_owned(), &"alice.near".to_owned());
+        env::state_write(&ContractV0 {
+            registry: accounts(0),
+            address_by_networks,
+            ipfs: LazyOption::new(StorageKey::Ipfs, Some(&"bafy".to_owned())),
+            text_records: HashMap::from([("email".to_owned(), "alice@navara.xyz".to_owned())]),
+            icon: LazyOption::new(StorageKey::Icon, None),
+        });
+
+        let contract = Contract::migrate(2);
+        assert_eq!(contract.version(), 2);
+        assert_eq!(contract.registry, accounts(0));
+        assert_eq!(contract.address_by_networks.get(&"near".to_owned()), Some("alice.near".to_owned()));
+        assert_eq!(contract.ipfs.get(), Some("bafy".to_owned()));
+        assert_eq!(contract.text_records.get("email"), Some(&"alice@navara.xyz".to_owned()));
+    }
+
+    #[test]
+    fn test_on_upgrade() {
+        let context = get_context(accounts(0));
+        testing_env!(
+            context.build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Successful(vec![])]
+        );
+        let mut contract = Contract::new(accounts(1), Some(1));
+        assert!(contract.on_upgrade(U128(10)));
+        assert!(near_sdk::test_utils::get_created_receipts().is_empty());
+
+        testing_env!(
+            context.build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Failed]
+        );
+        assert!(!contract.on_upgrade(U128(10)));
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, accounts(0));
+    }
+
 }
\ No newline at end of file
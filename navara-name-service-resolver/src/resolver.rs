@@ -10,6 +10,7 @@ impl Contract {
     }
 
     pub fn set_addresses(&mut self, addresses: HashMap<String, String>) {
+        Self::require_unpaused();
         Self::require_owner();
         for (key, value) in addresses {
             self.address_by_networks.insert(&key, &value);
@@ -3,6 +3,7 @@ use crate::*;
 #[near_bindgen]
 impl Contract {
     pub fn set_text_records(&mut self, records: HashMap<String, String>) {
+        Self::require_unpaused();
         Self::require_owner();
         for (key, value) in records {
             self.text_records.insert(key, value);
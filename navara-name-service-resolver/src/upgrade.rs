@@ -0,0 +1,80 @@
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::{serde_json, Gas, PromiseResult};
+
+use crate::*;
+
+const MIGRATE_GAS: Gas = Gas(20_000_000_000_000);
+const ON_UPGRADE_GAS: Gas = Gas(10_000_000_000_000);
+
+/// Layout of resolvers deployed before versioning.
+#[derive(BorshDeserialize)]
+struct ContractV0 {
+    registry: AccountId,
+    address_by_networks: UnorderedMap<String, String>,
+    ipfs: LazyOption<String>,
+    text_records: HashMap<String, String>,
+    icon: LazyOption<String>,
+}
+
+impl From<ContractV0> for Contract {
+    fn from(old: ContractV0) -> Self {
+        Self {
+            registry: old.registry,
+            address_by_networks: old.address_by_networks,
+            ipfs: old.ipfs,
+            text_records: old.text_records,
+            icon: old.icon,
+            version: 0,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Replaces the code of this resolver with `code` and migrates the state
+    /// to `version`. Only the registry can upgrade its resolvers. The
+    /// attached deposit pays for the new code, and is returned to the
+    /// registry when the upgrade fails. Resolves to whether it upgraded.
+    #[payable]
+    pub fn upgrade(&mut self, version: u32, code: Base64VecU8) -> Promise {
+        self.only_registry();
+        assert!(version > self.version, "Resolver already on a newer version");
+        Promise::new(env::current_account_id())
+            .deploy_contract(code.into())
+            .function_call(
+                "migrate".to_owned(),
+                serde_json::json!({ "version": version }).to_string().into_bytes(),
+                0,
+                MIGRATE_GAS
+            )
+            .then(Self::ext(env::current_account_id())
+                .with_static_gas(ON_UPGRADE_GAS)
+                .on_upgrade(U128::from(env::attached_deposit())))
+    }
+
+    #[private]
+    pub fn on_upgrade(&mut self, deposited: U128) -> bool {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return true;
+        }
+        if deposited.0 > 0 {
+            Promise::new(self.registry.to_owned()).transfer(deposited.0);
+        }
+        false
+    }
+
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(version: u32) -> Self {
+        let state = env::storage_read(b"STATE").expect("Not initialized");
+        let mut contract = Contract::try_from_slice(&state)
+            .unwrap_or_else(|_| ContractV0::try_from_slice(&state).expect("Unknown state").into());
+        assert!(version > contract.version, "Resolver already on a newer version");
+        contract.version = version;
+        contract
+    }
+}
@@ -0,0 +1,137 @@
+//! Stand-in for the NEAR price oracle, to test USD pricing of the registry
+//! locally or on testnet. Returns whatever prices its owner sets.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedMap;
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId, BorshStorageKey, PanicOnDefault};
+
+const RECENCY_DURATION_SEC: u32 = 90;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Price {
+    pub multiplier: U128,
+    pub decimals: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetOptionalPrice {
+    pub asset_id: String,
+    pub price: Option<Price>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceData {
+    pub timestamp: U64,
+    pub recency_duration_sec: u32,
+    pub prices: Vec<AssetOptionalPrice>,
+}
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct Contract {
+    owner_id: AccountId,
+    prices: UnorderedMap<String, Price>,
+    /// Reported instead of the block time, to test stale quotes.
+    timestamp: Option<u64>,
+}
+
+#[derive(BorshSerialize, BorshStorageKey)]
+enum StorageKey {
+    Prices,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[init]
+    pub fn new(owner_id: AccountId) -> Self {
+        require!(!env::state_exists(), "Already initialized");
+        Self {
+            owner_id,
+            prices: UnorderedMap::new(StorageKey::Prices),
+            timestamp: None,
+        }
+    }
+
+    fn only_owner(&self) {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Owner only")
+    }
+
+    /// Sets the price of `asset_id`, or removes it with `None`.
+    pub fn set_price(&mut self, asset_id: String, price: Option<Price>) {
+        self.only_owner();
+        match price {
+            Some(price) => self.prices.insert(&asset_id, &price),
+            None => self.prices.remove(&asset_id),
+        };
+    }
+
+    /// Timestamp in nanoseconds reported by `get_price_data`, the block time
+    /// when `None`.
+    pub fn set_timestamp(&mut self, timestamp: Option<U64>) {
+        self.only_owner();
+        self.timestamp = timestamp.map(u64::from);
+    }
+
+    pub fn get_price_data(&self, asset_ids: Option<Vec<String>>) -> PriceData {
+        let asset_ids = asset_ids.unwrap_or_else(|| self.prices.keys().collect());
+        PriceData {
+            timestamp: U64::from(self.timestamp.unwrap_or_else(env::block_timestamp)),
+            recency_duration_sec: RECENCY_DURATION_SEC,
+            prices: asset_ids
+                .into_iter()
+                .map(|asset_id| AssetOptionalPrice {
+                    price: self.prices.get(&asset_id),
+                    asset_id,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    #[test]
+    fn test_get_price_data() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.block_timestamp(42).build());
+        let mut contract = Contract::new(accounts(0));
+        let price = Price { multiplier: U128(25_000), decimals: 28 };
+        contract.set_price("wrap.near".to_string(), Some(price.to_owned()));
+
+        let price_data = contract.get_price_data(Some(vec!["wrap.near".to_string(), "usdc.near".to_string()]));
+        assert_eq!(price_data.timestamp, U64(42));
+        assert_eq!(price_data.prices[0].price, Some(price));
+        assert_eq!(price_data.prices[1].price, None);
+
+        contract.set_timestamp(Some(U64(1)));
+        assert_eq!(contract.get_price_data(None).timestamp, U64(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Owner only")]
+    fn test_set_price_owner_only() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0));
+        contract.set_price("wrap.near".to_string(), None);
+    }
+}
@@ -1 +1,6 @@
-near call nns.navara.testnet register '{ "token_id": "manhnguyenvan", "token_owner_id": "manhnguyenvan.testnet" }' --accountId manhnguyenvan.testnet --amount 2 --gas=300000000000000
\ No newline at end of file
+# commit, wait for the commitment to mature, then reveal
+
+COMMITMENT=$(near view nns.navara.testnet make_commitment '{ "name": "manhnguyenvan", "owner_id": "manhnguyenvan.testnet", "salt": "'"$1"'" }' | tail -1)
+near call nns.navara.testnet commit "{ \"commitment\": $COMMITMENT }" --accountId manhnguyenvan.testnet --amount 0.01
+sleep 60
+near call nns.navara.testnet reveal_register '{ "name": "manhnguyenvan", "owner_id": "manhnguyenvan.testnet", "salt": "'"$1"'" }' --accountId manhnguyenvan.testnet --amount 2 --gas=300000000000000
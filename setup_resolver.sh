@@ -1 +1 @@
-near call nns.navara.testnet setup '{ "token_id": "manhnguyenvan" }' --accountId manhnguyenvan.testnet --amount 2 --gas=300000000000000
\ No newline at end of file
+near call nns.navara.testnet setup '{ "token_id": "manhnguyenvan", "version": null }' --accountId manhnguyenvan.testnet --amount 2 --gas=300000000000000
\ No newline at end of file
@@ -0,0 +1 @@
+near call nns.navara.testnet upgrade_resolver '{ "token_id": "manhnguyenvan", "version": 2 }' --accountId manhnguyenvan.testnet --amount 1 --gas=300000000000000
\ No newline at end of file
@@ -0,0 +1,8 @@
+# upload out/resolver.wasm as a new resolver version, version 1 is the
+# resolver embedded in the registry
+
+VERSION=${1:?usage: upload_resolver_code.sh <version greater than 1>}
+HASH=$(python3 -c "import hashlib,base58,sys; print(base58.b58encode(hashlib.sha256(open('out/resolver.wasm','rb').read()).digest()).decode())")
+CODE=$(base64 -w 0 out/resolver.wasm)
+
+near call nns.navara.testnet upload_resolver_code "{ \"version\": $VERSION, \"hash\": \"$HASH\", \"code\": \"$CODE\" }" --accountId navara.testnet --amount 5 --gas=300000000000000